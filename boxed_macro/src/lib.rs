@@ -1,23 +1,141 @@
 extern crate proc_macro;
 
 use proc_macro::TokenStream;
-use quote::quote;
-use syn::DeriveInput;
+use quote::{format_ident, quote, quote_spanned};
+use syn::{parse_macro_input, Data, DeriveInput, LitStr, Signature};
 
-#[proc_macro_derive(Boxed)]
+/// `Type::boxed()` from `Default`,
+/// or `Type::new_boxed(args)` for each `#[boxed(new = "new(args)")]` constructor
+#[proc_macro_derive(Boxed, attributes(boxed))]
 pub fn boxed_macro_derive(input: TokenStream) -> TokenStream {
-    let ast: DeriveInput = syn::parse(input).unwrap();
+    let ast = parse_macro_input!(input as DeriveInput);
     impl_boxed_macro(&ast)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
 }
 
-fn impl_boxed_macro(ast: &syn::DeriveInput) -> TokenStream {
+fn impl_boxed_macro(ast: &syn::DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
     let name = &ast.ident;
-    let gen = quote! {
-        impl BoxedDefault for #name {
-            fn boxed() -> Box<dyn LineBuilder> {
-                Box::new(#name::default())
+    let kind = match &ast.data {
+        Data::Struct(_) => None,
+        Data::Enum(_) => Some("an enum"),
+        Data::Union(_) => Some("a union"),
+    };
+    if let Some(kind) = kind {
+        return Err(syn::Error::new_spanned(
+            name,
+            format!(
+                "`Boxed` can only be derived for structs, `{}` is {}, implement BoxedDefault by hand",
+                name, kind
+            ),
+        ));
+    }
+    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+    let constructors = constructors(ast)?;
+    // a boxed LineBuilder is 'static, generic types only get it with the bounds of its impl
+    let self_bound = quote! { Self: LineBuilder + Sized };
+    if constructors.is_empty() {
+        let predicates = where_clause.iter().flat_map(|w| w.predicates.iter());
+        // a missing Default is reported on the type, not inside the generated impl
+        let default_bound = quote_spanned! {name.span()=> Self: Default };
+        let where_clause = quote! { where #(#predicates,)* #self_bound, #default_bound };
+        return Ok(quote! {
+            impl #impl_generics BoxedDefault for #name #ty_generics #where_clause {
+                fn boxed() -> Box<dyn LineBuilder> {
+                    Box::new(Self::default())
+                }
+            }
+        });
+    }
+    let fns = constructors.iter().map(|sig| {
+        let new = &sig.ident;
+        let boxed = format_ident!("{}_boxed", new);
+        let inputs = &sig.inputs;
+        let args = sig.inputs.iter().map(|arg| match arg {
+            syn::FnArg::Typed(pat) => &pat.pat,
+            syn::FnArg::Receiver(_) => unreachable!("checked in constructors"),
+        });
+        quote! {
+            fn #boxed(#inputs) -> Box<dyn LineBuilder>
+            where
+                #self_bound,
+            {
+                Box::new(Self::#new(#(#args),*))
             }
         }
-    };
-    gen.into()
+    });
+    Ok(quote! {
+        impl #impl_generics #name #ty_generics #where_clause {
+            #(#fns)*
+        }
+    })
+}
+
+/// `#[boxed(new = "new(expression: &str)")]`, the signature of an associated fn returning Self
+fn constructors(ast: &syn::DeriveInput) -> syn::Result<Vec<Signature>> {
+    let mut constructors = Vec::new();
+    for attr in ast.attrs.iter().filter(|a| a.path().is_ident("boxed")) {
+        attr.parse_nested_meta(|meta| {
+            if !meta.path.is_ident("new") {
+                return Err(meta.error("expected `new = \"...\"`"));
+            }
+            let lit: LitStr = meta.value()?.parse()?;
+            let sig: Signature = syn::parse_str(&format!("fn {}", lit.value())).map_err(|e| {
+                syn::Error::new(
+                    lit.span(),
+                    format!("invalid constructor `{}`: {}", lit.value(), e),
+                )
+            })?;
+            // the signature is parsed from the string, it has no span in the source
+            if let Some(syn::FnArg::Receiver(_)) = sig.inputs.first() {
+                return Err(syn::Error::new(lit.span(), "a constructor takes no self"));
+            }
+            constructors.push(sig);
+            Ok(())
+        })?;
+    }
+    Ok(constructors)
+}
+
+#[cfg(test)]
+mod test {
+    use syn::parse_quote;
+
+    use super::impl_boxed_macro;
+
+    fn error(ast: syn::DeriveInput) -> String {
+        impl_boxed_macro(&ast).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn test_not_struct() {
+        assert_eq!(
+            error(parse_quote! { enum SortLB { Name, Size } }),
+            "`Boxed` can only be derived for structs, `SortLB` is an enum, implement BoxedDefault by hand"
+        );
+        assert!(error(parse_quote! { union RawLB { a: u8 } }).contains("is a union"));
+    }
+
+    #[test]
+    fn test_bad_constructor() {
+        assert_eq!(
+            error(parse_quote! {
+                #[boxed(build = "new()")]
+                struct CaptureLB;
+            }),
+            "expected `new = \"...\"`"
+        );
+        assert_eq!(
+            error(parse_quote! {
+                #[boxed(new = "new(&self)")]
+                struct CaptureLB;
+            }),
+            "a constructor takes no self"
+        );
+        assert!(error(parse_quote! {
+            #[boxed(new = "new(")]
+            struct CaptureLB;
+        })
+        .starts_with("invalid constructor `new(`"));
+    }
 }
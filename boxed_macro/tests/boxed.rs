@@ -0,0 +1,94 @@
+use std::fmt::Display;
+
+use boxed_macro::Boxed;
+
+// the derive expects these two traits in scope, as in output_format.rs
+trait LineBuilder: Send + Sync + 'static {
+    fn output(&self) -> Vec<String>;
+}
+
+trait BoxedDefault {
+    fn boxed() -> Box<dyn LineBuilder>;
+}
+
+#[derive(Default, Boxed)]
+struct EmptyLB;
+
+impl LineBuilder for EmptyLB {
+    fn output(&self) -> Vec<String> {
+        vec![String::new()]
+    }
+}
+
+#[derive(Boxed)]
+#[boxed(new = "new(label: &str)")]
+#[boxed(new = "repeat(label: &str, times: usize)")]
+struct LabelLB {
+    inner: Vec<String>,
+}
+
+impl LabelLB {
+    fn new(label: &str) -> Self {
+        Self::repeat(label, 1)
+    }
+    fn repeat(label: &str, times: usize) -> Self {
+        Self {
+            inner: vec![label.to_string(); times],
+        }
+    }
+}
+
+impl LineBuilder for LabelLB {
+    fn output(&self) -> Vec<String> {
+        self.inner.clone()
+    }
+}
+
+#[derive(Default, Boxed)]
+struct ValueLB<T>
+where
+    T: Display,
+{
+    value: T,
+}
+
+impl<T: Display + Send + Sync + 'static> LineBuilder for ValueLB<T> {
+    fn output(&self) -> Vec<String> {
+        vec![self.value.to_string()]
+    }
+}
+
+#[derive(Boxed)]
+#[boxed(new = "new(value: T)")]
+struct PairLB<T: Display + Clone> {
+    value: T,
+}
+
+impl<T: Display + Clone> PairLB<T> {
+    fn new(value: T) -> Self {
+        Self { value }
+    }
+}
+
+impl<T: Display + Clone + Send + Sync + 'static> LineBuilder for PairLB<T> {
+    fn output(&self) -> Vec<String> {
+        vec![self.value.to_string(), self.value.clone().to_string()]
+    }
+}
+
+#[test]
+fn test_default() {
+    assert_eq!(EmptyLB::boxed().output(), vec![""]);
+}
+
+#[test]
+fn test_named_constructors() {
+    assert_eq!(LabelLB::new_boxed("label").output(), vec!["label"]);
+    assert_eq!(LabelLB::repeat_boxed("a", 2).output(), vec!["a", "a"]);
+}
+
+#[test]
+fn test_generics() {
+    assert_eq!(ValueLB::<u8>::boxed().output(), vec!["0"]);
+    assert_eq!(PairLB::new_boxed(7).output(), vec!["7", "7"]);
+}
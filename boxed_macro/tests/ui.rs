@@ -0,0 +1,10 @@
+// the messages of a wrong derive are compared with the .stderr files next to the cases,
+// TRYBUILD=overwrite writes them again
+#[test]
+fn test_ui() {
+    let t = trybuild::TestCases::new();
+    t.pass("tests/ui/generic.rs");
+    t.compile_fail("tests/ui/bad_constructor.rs");
+    t.compile_fail("tests/ui/enum.rs");
+    t.compile_fail("tests/ui/no_default.rs");
+}
@@ -0,0 +1,23 @@
+use boxed_macro::Boxed;
+
+trait LineBuilder: Send + Sync + 'static {
+    fn output(&self) -> Vec<String>;
+}
+
+trait BoxedDefault {
+    fn boxed() -> Box<dyn LineBuilder>;
+}
+
+#[derive(Boxed)]
+#[boxed(new = "new(&self)")]
+struct CaptureLB;
+
+#[derive(Boxed)]
+#[boxed(build = "new()")]
+struct LabelLB;
+
+#[derive(Boxed)]
+#[boxed(new = "new(")]
+struct ProgressLB;
+
+fn main() {}
@@ -0,0 +1,17 @@
+use boxed_macro::Boxed;
+
+trait LineBuilder: Send + Sync + 'static {
+    fn output(&self) -> Vec<String>;
+}
+
+trait BoxedDefault {
+    fn boxed() -> Box<dyn LineBuilder>;
+}
+
+#[derive(Boxed)]
+enum SortLB {
+    Name,
+    Size,
+}
+
+fn main() {}
@@ -0,0 +1,48 @@
+use std::fmt::Display;
+
+use boxed_macro::Boxed;
+
+trait LineBuilder: Send + Sync + 'static {
+    fn output(&self) -> Vec<String>;
+}
+
+trait BoxedDefault {
+    fn boxed() -> Box<dyn LineBuilder>;
+}
+
+#[derive(Default, Boxed)]
+struct ValueLB<T>
+where
+    T: Display,
+{
+    value: T,
+}
+
+impl<T: Display + Send + Sync + 'static> LineBuilder for ValueLB<T> {
+    fn output(&self) -> Vec<String> {
+        vec![self.value.to_string()]
+    }
+}
+
+#[derive(Boxed)]
+#[boxed(new = "new(value: T)")]
+struct PairLB<T: Display> {
+    value: T,
+}
+
+impl<T: Display> PairLB<T> {
+    fn new(value: T) -> Self {
+        Self { value }
+    }
+}
+
+impl<T: Display + Send + Sync + 'static> LineBuilder for PairLB<T> {
+    fn output(&self) -> Vec<String> {
+        vec![self.value.to_string(); 2]
+    }
+}
+
+fn main() {
+    assert_eq!(ValueLB::<u8>::boxed().output(), vec!["0"]);
+    assert_eq!(PairLB::new_boxed("a").output(), vec!["a", "a"]);
+}
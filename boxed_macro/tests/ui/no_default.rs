@@ -0,0 +1,23 @@
+use boxed_macro::Boxed;
+
+trait LineBuilder: Send + Sync + 'static {
+    fn output(&self) -> Vec<String>;
+}
+
+trait BoxedDefault {
+    fn boxed() -> Box<dyn LineBuilder>;
+}
+
+// neither Default nor a #[boxed(new = "...")] constructor
+#[derive(Boxed)]
+struct StatusLB {
+    inner: String,
+}
+
+impl LineBuilder for StatusLB {
+    fn output(&self) -> Vec<String> {
+        vec![self.inner.clone()]
+    }
+}
+
+fn main() {}
@@ -0,0 +1,84 @@
+use std::{fs::File, io::Read, path::Path};
+
+use sha2::{Digest, Sha256};
+
+/// read in chunks, extracted files may be larger than the memory
+const CHUNK: usize = 64 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashKind {
+    Crc32,
+    Sha256,
+}
+
+impl HashKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            HashKind::Crc32 => "CRC32",
+            HashKind::Sha256 => "SHA-256",
+        }
+    }
+}
+
+/// CRC32 in upper case like 7z prints it, SHA-256 in lower case like sha256sum
+pub fn file_checksum(path: &Path, kind: HashKind) -> std::io::Result<String> {
+    checksum(File::open(path)?, kind)
+}
+
+fn checksum(mut reader: impl Read, kind: HashKind) -> std::io::Result<String> {
+    let mut buf = vec![0u8; CHUNK];
+    let mut crc = crc32fast::Hasher::new();
+    let mut sha = Sha256::new();
+    loop {
+        let n = match reader.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        match kind {
+            HashKind::Crc32 => crc.update(&buf[..n]),
+            HashKind::Sha256 => sha.update(&buf[..n]),
+        }
+    }
+    Ok(match kind {
+        HashKind::Crc32 => format!("{:08X}", crc.finalize()),
+        HashKind::Sha256 => sha
+            .finalize()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{checksum, HashKind};
+
+    #[test]
+    fn test_checksum() {
+        let hash = |data: &[u8], kind| checksum(data, kind).unwrap();
+        assert_eq!(hash(b"123456789", HashKind::Crc32), "CBF43926");
+        assert_eq!(hash(b"", HashKind::Crc32), "00000000");
+        assert_eq!(
+            hash(b"", HashKind::Sha256),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            hash(b"abc", HashKind::Sha256),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        // across chunks and blocks
+        assert_eq!(
+            hash(&vec![b'a'; 1_000_000], HashKind::Sha256),
+            "cdc76e5c9914fb9281a1c7e284d73e67f1809a48a497200e046d39ccc7112cd0"
+        );
+        assert_eq!(
+            hash(
+                b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq",
+                HashKind::Sha256
+            ),
+            "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1"
+        );
+    }
+}
@@ -0,0 +1,60 @@
+use std::io::{stdout, ErrorKind, Write};
+
+use log::info;
+use tokio::sync::mpsc;
+
+use crate::z7::{Operation, Pushment};
+
+/// print the document to stdout instead of nvim,
+/// quit after the first command finished or asked for a password,
+/// the password is given by --password and passed to 7z directly.
+/// Quit waits for the command, so the exit code has its result
+pub struct Headless;
+
+impl Headless {
+    /// with exports (--json, --export-list), the file list is exported instead of printed,
+    /// then quit once the list finished
+    pub async fn start(
+        mut doc_recv: mpsc::Receiver<Pushment>,
+        oper_sender: mpsc::Sender<Operation>,
+        mut exports: Vec<Operation>,
+    ) -> tokio::io::Result<()> {
+        let print = exports.is_empty();
+        // Quit has been sent, wait for its None
+        let mut quitting = false;
+        while let Some(pushment) = doc_recv.recv().await {
+            match pushment {
+                Pushment::Full(_, _) if quitting => {}
+                // a password prompt has the cursor, print it anyway
+                Pushment::Full(lines, cursor) if print || cursor.is_some() => {
+                    quitting = true;
+                    {
+                        let mut out = stdout().lock();
+                        for line in lines {
+                            writeln!(out, "{}", line)?;
+                        }
+                        out.flush()?;
+                    }
+                    // stdin is closed on quit, 7z gives up the password prompt
+                    if oper_sender.send(Operation::Quit).await.is_err() {
+                        return Err(ErrorKind::BrokenPipe.into());
+                    }
+                }
+                Pushment::Full(_, _) => {
+                    quitting = true;
+                    // all wait for the running list, Quit pushes None at last
+                    for oper in exports.drain(..).chain([Operation::Quit]) {
+                        if oper_sender.send(oper).await.is_err() {
+                            return Err(ErrorKind::BrokenPipe.into());
+                        }
+                    }
+                }
+                Pushment::Line(_, _) | Pushment::Detail(_) => {}
+                Pushment::None => break,
+            }
+        }
+        info!("headless quit");
+        // return error, then other task will be canceled
+        tokio::io::Result::<()>::Err(ErrorKind::Other.into())
+    }
+}
@@ -1,27 +1,115 @@
-use clap::Parser;
+use log4rs::{
+    append::file::FileAppender,
+    config::{Appender, Config, Root},
+    encode::pattern::PatternEncoder,
+};
 use options::Options;
 use tokio::{sync::mpsc, try_join};
 use z7::{Operation, Pushment, Z7};
 
-use crate::nvim::Nvim;
+use crate::{headless::Headless, nvim::Nvim};
+mod checksum;
+mod headless;
 mod nvim;
 mod options;
 mod output_format;
 mod z7;
 
+/// file logging only, the terminal belongs to nvim
+fn init_log(opt: &Options) -> Result<(), String> {
+    let Some(path) = &opt.log_file else {
+        return Ok(());
+    };
+    let appender = FileAppender::builder()
+        .encoder(Box::new(PatternEncoder::new("{d} {l} {t} - {m}{n}")))
+        .build(path)
+        .map_err(|e| format!("can not open log file {}: {}", path.display(), e))?;
+    let config = Config::builder()
+        .appender(Appender::builder().build("file", Box::new(appender)))
+        .build(Root::builder().appender("file").build(opt.log_level))
+        .map_err(|e| format!("invalid log config: {}", e))?;
+    log4rs::init_config(config).map_err(|e| format!("can not init log: {}", e))?;
+    Ok(())
+}
+
 #[tokio::main]
 async fn main() {
-    let opt = Options::parse();
-    log4rs::init_file(
-        "/home/kamo-death/.config/7zvui/log4rs.yaml",
-        Default::default(),
-    )
-    .unwrap();
+    let mut opt = match Options::load() {
+        Ok(opt) => opt,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    if z7::find_binary(&opt.seven_zip_bin).is_none() {
+        eprintln!(
+            "7z binary not found: {}, install p7zip or set it with --bin",
+            opt.seven_zip_bin
+        );
+        std::process::exit(1);
+    }
+    if let Err(e) = z7::check_extra_args(&opt.seven_zip_args) {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+    if let Err(e) = z7::check_output_dir(&opt) {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+    let keymaps = match nvim::keymaps(&opt.keymaps) {
+        Ok(keymaps) => keymaps,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    // removed at the end of main, exit() would skip it
+    let temp_archive = match opt.file.fetch() {
+        Ok(temp) => temp,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    if let Err(e) = opt.file.validate() {
+        eprintln!("{}", e);
+        drop(temp_archive);
+        std::process::exit(1);
+    }
+    if let Err(e) = init_log(&opt) {
+        eprintln!("{}", e);
+        drop(temp_archive);
+        std::process::exit(1);
+    }
     let (doc_sender, doc_recv) = mpsc::channel::<Pushment>(1);
     let (oper_sender, oper_recv) = mpsc::channel::<Operation>(1);
     let mut z7 = Z7::new(doc_sender, &opt);
-    let _ = try_join!(
-        z7.start(oper_recv, oper_sender.clone()),
-        Nvim::start(doc_recv, oper_sender)
-    );
+    z7.set_keys(nvim::key_labels(&keymaps)).await;
+    if opt.headless {
+        let exports = opt
+            .json
+            .clone()
+            .map(Operation::ExportJson)
+            .into_iter()
+            .chain(
+                opt.export_list
+                    .clone()
+                    .map(|path| Operation::ExportList(path, opt.export_destinations)),
+            )
+            .collect();
+        let _ = try_join!(
+            z7.start(oper_recv, oper_sender.clone()),
+            Headless::start(doc_recv, oper_sender, exports)
+        );
+    } else {
+        let _ = try_join!(
+            z7.start(oper_recv, oper_sender.clone()),
+            Nvim::start(doc_recv, oper_sender, &opt, keymaps)
+        );
+    }
+    let code = z7.exit_code().await;
+    // nvim may quit in the middle of a command, do not leave 7z behind
+    z7.shutdown().await;
+    drop(temp_archive);
+    std::process::exit(code as i32);
 }
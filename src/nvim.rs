@@ -1,22 +1,37 @@
 use std::{
+    collections::HashMap,
     fmt::Debug,
-    io::{stdout, ErrorKind},
-    path::Path,
+    io::{stdout, ErrorKind, IsTerminal},
+    os::unix::fs::FileTypeExt,
+    path::{Path, PathBuf},
+    process::Stdio,
+    sync::{Arc, Mutex},
     time::Duration,
 };
 
 use async_trait::async_trait;
-use log::{error, info};
+use log::{error, info, warn};
 use nvim_rs::{
-    compat::tokio::Compat, create::tokio::new_path, error::CallError, Handler, Neovim, Value,
+    compat::tokio::Compat, create::tokio::new_path, error::CallError, Buffer, Handler, Neovim,
+    Value,
 };
 use parity_tokio_ipc::Connection;
-use tokio::{io::WriteHalf, process::Command, sync::mpsc, time::sleep, try_join};
+use tokio::{
+    io::{AsyncBufReadExt, BufReader, WriteHalf},
+    process::Command,
+    sync::{mpsc, oneshot},
+    time::{sleep, Instant},
+    try_join,
+};
 
-use crate::z7::{Operation, Pushment};
+use crate::{
+    checksum::HashKind,
+    options::Options,
+    output_format::{OverwriteMode, SortKey},
+    z7::{complete_path, Operation, Pushment},
+};
 
 // const OUTPUT_FILE: &str = "handler_drop.txt";
-const NVIMPATH: &str = "nvim";
 
 pub struct BufLineChanges {
     line_start: u64,
@@ -25,22 +40,31 @@ pub struct BufLineChanges {
     content: Vec<String>,
 }
 
-impl From<Vec<Value>> for BufLineChanges {
-    fn from(args: Vec<Value>) -> Self {
-        let line_start = args[2].as_u64().unwrap();
-        let line_end = args[3].as_u64().unwrap();
-        let buf_id = args[1].as_i64().unwrap();
-        let content = args[4].as_array().unwrap();
-        let mut content_vec = Vec::new();
-        content.iter().for_each(|v| {
-            content_vec.push(v.as_str().unwrap().to_string());
-        });
-        Self {
+// [buf, changedtick, firstline, lastline, linedata, more]
+impl TryFrom<Vec<Value>> for BufLineChanges {
+    type Error = String;
+
+    fn try_from(args: Vec<Value>) -> Result<Self, Self::Error> {
+        let malformed = || format!("malformed buf lines event: {:?}", args);
+        let line_start = args.get(2).and_then(|v| v.as_u64()).ok_or_else(malformed)?;
+        let line_end = args.get(3).and_then(|v| v.as_u64()).ok_or_else(malformed)?;
+        let buf_id = args.get(1).and_then(|v| v.as_i64()).ok_or_else(malformed)?;
+        let content = args
+            .get(4)
+            .and_then(|v| v.as_array())
+            .and_then(|lines| {
+                lines
+                    .iter()
+                    .map(|v| v.as_str().map(|s| s.to_string()))
+                    .collect::<Option<Vec<_>>>()
+            })
+            .ok_or_else(malformed)?;
+        Ok(Self {
             line_start,
             line_end,
             buf_id,
-            content: content_vec,
-        }
+            content,
+        })
     }
 }
 
@@ -57,12 +81,63 @@ impl Debug for BufLineChanges {
 #[derive(Clone)]
 struct NeovimHandler {
     oper_sender: mpsc::Sender<Operation>,
+    // the last sort key, "nvim_sort_event" cycles from it
+    sort_key: Arc<Mutex<Option<SortKey>>>,
+    // the current overwrite mode, "nvim_overwrite_event" cycles from it
+    overwrite: Arc<Mutex<OverwriteMode>>,
+    // relative paths on "Extract to: " are completed from here
+    archive_dir: PathBuf,
+    // the prompt lines last sent, InsertLeave repeats them on every <Esc>
+    last_password: Arc<Mutex<LastSent>>,
+    last_extract_to: Arc<Mutex<LastSent>>,
 }
 
 impl NeovimHandler {
-    pub fn new(oper_sender: mpsc::Sender<Operation>) -> Self {
-        Self { oper_sender }
+    pub fn new(oper_sender: mpsc::Sender<Operation>, archive_dir: PathBuf) -> Self {
+        Self {
+            oper_sender,
+            archive_dir,
+            sort_key: Arc::new(Mutex::new(None)),
+            overwrite: Arc::new(Mutex::new(OverwriteMode::default())),
+            last_password: Arc::new(Mutex::new(LastSent::default())),
+            last_extract_to: Arc::new(Mutex::new(LastSent::default())),
+        }
+    }
+}
+
+/// the same value again within it is dropped, a new one always goes
+const DEBOUNCE: Duration = Duration::from_millis(150);
+
+/// a value sent for a prompt line and when
+#[derive(Default)]
+struct LastSent(Option<(String, Instant)>);
+
+impl LastSent {
+    /// true if the value should be sent, then it is the last one
+    fn update(&mut self, value: &str, now: Instant) -> bool {
+        if let Some((last, at)) = &self.0 {
+            // a later one with the same value may still be wanted, e.g. 7z asked again
+            if last == value && now.duration_since(*at) < DEBOUNCE {
+                return false;
+            }
+        }
+        self.0 = Some((value.to_string(), now));
+        true
     }
+
+    /// true if the value differs from the last one however long ago, then it is the last one
+    fn changed(&mut self, value: &str, now: Instant) -> bool {
+        if self.0.as_ref().is_some_and(|(last, _)| last == value) {
+            return false;
+        }
+        self.0 = Some((value.to_string(), now));
+        true
+    }
+}
+
+/// the path on a pushed "Extract to: " line
+fn extract_to_value(line: &str) -> Option<&str> {
+    line.strip_prefix("Extract to: ").map(str::trim)
 }
 
 struct CursorAt {
@@ -72,12 +147,50 @@ struct CursorAt {
 }
 
 // [Array([String(Utf8String { s: Ok("n") }), Array([Integer(PosInt(1)), Integer(PosInt(0))])])]
-impl From<Vec<Value>> for CursorAt {
-    fn from(args: Vec<Value>) -> Self {
-        let args = args[0].as_array().unwrap()[1].as_array().unwrap();
-        let col = args[0].as_i64().unwrap();
-        let row = args[1].as_i64().unwrap();
-        Self { col, row }
+impl TryFrom<Vec<Value>> for CursorAt {
+    type Error = String;
+
+    fn try_from(args: Vec<Value>) -> Result<Self, Self::Error> {
+        let malformed = || format!("malformed cursor: {:?}", args);
+        let pos = args
+            .first()
+            .and_then(|v| v.as_array())
+            .and_then(|v| v.get(1))
+            .and_then(|v| v.as_array())
+            .ok_or_else(malformed)?;
+        let col = pos.first().and_then(|v| v.as_i64()).ok_or_else(malformed)?;
+        let row = pos.get(1).and_then(|v| v.as_i64()).ok_or_else(malformed)?;
+        Ok(Self { col, row })
+    }
+}
+
+impl NeovimHandler {
+    /// an edited file row renames the file in the archive after confirmed,
+    /// declined, the buffer is rendered again to drop the edit
+    async fn rename_line(&self, row: usize, line: &str, nvim: &Neovim<<Self as Handler>::Writer>) {
+        let (sender, recv) = oneshot::channel();
+        let target = Operation::RenameTarget(row, line.to_string(), sender);
+        if self.oper_sender.send(target).await.is_err() {
+            return;
+        }
+        let Some((old, new)) = recv.await.ok().flatten() else {
+            return;
+        };
+        let answer = nvim
+            .call_function(
+                "confirm",
+                vec![
+                    Value::from(format!("Rename {} to {} in archive?", old, new)),
+                    Value::from("&Yes\n&No"),
+                    Value::from(2),
+                ],
+            )
+            .await;
+        let oper = match answer.ok().and_then(|a| a.as_i64()) {
+            Some(1) => Operation::Rename { old, new },
+            _ => Operation::Render,
+        };
+        let _ = self.oper_sender.send(oper).await;
     }
 }
 
@@ -86,29 +199,137 @@ impl Handler for NeovimHandler {
     // type Writer = Compat<WriteHalf<Connection>>;
     type Writer = Compat<WriteHalf<Connection>>;
 
+    async fn handle_request(
+        &self,
+        name: String,
+        args: Vec<Value>,
+        _nvim: Neovim<Self::Writer>,
+    ) -> Result<Value, Value> {
+        match name.as_str() {
+            // the completefunc of "Extract to: ", args is the partial path
+            "nvim_complete_path" => {
+                let partial = args.first().and_then(|a| a.as_str()).unwrap_or_default();
+                Ok(Value::Array(
+                    complete_path(partial, &self.archive_dir)
+                        .into_iter()
+                        .map(Value::from)
+                        .collect(),
+                ))
+            }
+            // the archive path of a rendered line, empty if it is not a file row
+            "nvim_file_path" => {
+                let line = args.first().and_then(|a| a.as_str()).unwrap_or_default();
+                let (sender, recv) = oneshot::channel();
+                if self
+                    .oper_sender
+                    .send(Operation::FilePath(line.to_string(), sender))
+                    .await
+                    .is_err()
+                {
+                    return Err(Value::from("operation channel closed"));
+                }
+                Ok(Value::from(recv.await.ok().flatten().unwrap_or_default()))
+            }
+            _ => Err(Value::from(format!("unknown request: {}", name))),
+        }
+    }
+
     async fn handle_notify(&self, name: String, args: Vec<Value>, nvim: Neovim<Self::Writer>) {
         match name.as_str() {
             "nvim_buf_lines_event" => {
                 // info!("handle_notify: name: {}, args: {:?}", name, args);
-                let buf_line = BufLineChanges::from(args);
+                let buf_line = match BufLineChanges::try_from(args) {
+                    Ok(buf_line) => buf_line,
+                    Err(e) => {
+                        warn!("{}", e);
+                        return;
+                    }
+                };
                 if buf_line.content.len() == 1 && buf_line.content[0] == "Enter password: " {
                     let _ = self.oper_sender.try_send(Operation::Retry);
                 }
+                // a path pasted in normal mode, typing is sent on InsertLeave instead
+                let single_line =
+                    buf_line.content.len() == 1 && buf_line.line_end == buf_line.line_start + 1;
+                let Some(path) = buf_line
+                    .content
+                    .first()
+                    .filter(|_| single_line)
+                    .and_then(|l| extract_to_value(l))
+                    .filter(|p| !p.is_empty())
+                else {
+                    return;
+                };
+                let inserting = match nvim.get_mode().await {
+                    Ok(mode) => mode.iter().any(|(k, v)| {
+                        k.as_str() == Some("mode")
+                            && v.as_str()
+                                .is_some_and(|m| m.starts_with('i') || m.starts_with('R'))
+                    }),
+                    Err(e) => {
+                        error!("get mode error: {}", e);
+                        return;
+                    }
+                };
+                // pushed lines come back here too, only a path we do not know yet is sent
+                let send = !inserting
+                    && self
+                        .last_extract_to
+                        .lock()
+                        .unwrap()
+                        .changed(path, Instant::now());
+                if send {
+                    let _ = self
+                        .oper_sender
+                        .try_send(Operation::ExtractTo(path.to_string()));
+                }
             }
             "nvim_insert_leave_event" => {
                 // info!("handle_notify: name: {}, args: {:?}", name, args);
                 // find password from buf line, then send password to 7z
-                let buf = nvim.get_current_buf().await.expect("get current buf error");
-                let cursor = CursorAt::from(args);
-                let lines = buf
-                    .get_lines((cursor.col - 1).max(0), cursor.col + 1, false)
-                    .await
-                    .expect("get lines error");
+                let cursor = match CursorAt::try_from(args) {
+                    Ok(cursor) => cursor,
+                    Err(e) => {
+                        warn!("{}", e);
+                        return;
+                    }
+                };
+                let lines = match nvim.get_current_buf().await {
+                    Ok(buf) => buf
+                        .get_lines((cursor.col - 1).max(0), cursor.col + 1, false)
+                        .await
+                        .map_err(|e| e.to_string()),
+                    Err(e) => Err(e.to_string()),
+                };
+                let lines = match lines {
+                    Ok(lines) => lines,
+                    Err(e) => {
+                        error!("get lines on insert leave error: {}", e);
+                        return;
+                    }
+                };
+                let prompt = lines
+                    .iter()
+                    .any(|l| l.starts_with("Enter password: ") || l.starts_with("Extract to: "));
+                if !prompt {
+                    // the first one is the cursor line, a file row may have been renamed
+                    if let Some(line) = lines.first() {
+                        self.rename_line(cursor.col.max(0) as usize, line, &nvim)
+                            .await;
+                    }
+                    return;
+                }
                 for line in lines.into_iter() {
                     if line.starts_with("Enter password: ") {
                         let pwd = line.clone();
                         let pwd = pwd.trim_start_matches("Enter password:").trim().to_string();
-                        if !pwd.is_empty() {
+                        let send = !pwd.is_empty()
+                            && self
+                                .last_password
+                                .lock()
+                                .unwrap()
+                                .update(&pwd, Instant::now());
+                        if send {
                             let _ = self.oper_sender.try_send(Operation::Password(pwd));
                         }
                         break;
@@ -116,7 +337,13 @@ impl Handler for NeovimHandler {
                     if line.starts_with("Extract to: ") {
                         let path = line.clone();
                         let path = path.trim_start_matches("Extract to: ").trim().to_string();
-                        if !path.is_empty() {
+                        let send = !path.is_empty()
+                            && self
+                                .last_extract_to
+                                .lock()
+                                .unwrap()
+                                .update(&path, Instant::now());
+                        if send {
                             let _ = self.oper_sender.try_send(Operation::ExtractTo(path));
                         }
                         break;
@@ -127,13 +354,173 @@ impl Handler for NeovimHandler {
                 // info!("handle_notify: name: {}, args: {:?}", name, args);
                 let _ = self.oper_sender.try_send(Operation::Execute);
             }
-            "nvim_select_password_event" => {
-                info!("handle_notify: name: {}, args: {:?}", name, args);
-                let pwd = args[0].as_str();
-                if let Some(pwd) = pwd {
+            "nvim_extract_files_event" => {
+                // info!("handle_notify: name: {}, args: {:?}", name, args);
+                if let Some(lines) = args.first().and_then(|a| a.as_array()) {
+                    let lines = lines
+                        .iter()
+                        .filter_map(|l| l.as_str().map(|s| s.to_string()))
+                        .collect::<Vec<_>>();
+                    let _ = self.oper_sender.try_send(Operation::ExtractFiles(lines));
+                }
+            }
+            "nvim_peek_event" => {
+                // no lines from normal mode, the whole archive is peeked
+                if let Some(lines) = args.first().and_then(|a| a.as_array()) {
+                    let lines = lines
+                        .iter()
+                        .filter_map(|l| l.as_str().map(|s| s.to_string()))
+                        .collect::<Vec<_>>();
+                    let _ = self.oper_sender.try_send(Operation::Peek(lines));
+                }
+            }
+            "nvim_add_event" | "nvim_add_encrypted_event" => {
+                // info!("handle_notify: name: {}, args: {:?}", name, args);
+                if let Some(path) = args.first().and_then(|a| a.as_str()) {
+                    let pwd = args.get(1).and_then(|a| a.as_str()).map(String::from);
+                    let _ = self
+                        .oper_sender
+                        .try_send(Operation::Add(vec![path.to_string()], pwd));
+                }
+            }
+            "nvim_filter_event" => {
+                if let Some(pattern) = args.first().and_then(|a| a.as_str()) {
+                    let _ = self
+                        .oper_sender
+                        .try_send(Operation::Filter(pattern.to_string()));
+                }
+            }
+            "nvim_find_event" => {
+                let query = args.first().and_then(|a| a.as_str());
+                let from = args.get(1).and_then(|a| a.as_u64()).unwrap_or(0);
+                if let Some(query) = query.filter(|q| !q.trim().is_empty()) {
+                    let _ = self
+                        .oper_sender
+                        .try_send(Operation::FindFile(query.to_string(), from as usize));
+                }
+            }
+            "nvim_delete_event" => {
+                // info!("handle_notify: name: {}, args: {:?}", name, args);
+                if let Some(lines) = args.first().and_then(|a| a.as_array()) {
+                    let lines = lines
+                        .iter()
+                        .filter_map(|l| l.as_str().map(|s| s.to_string()))
+                        .collect::<Vec<_>>();
+                    let _ = self.oper_sender.try_send(Operation::Delete(lines));
+                }
+            }
+            "nvim_sort_event" => {
+                // info!("handle_notify: name: {}, args: {:?}", name, args);
+                let key = {
+                    let mut sort_key = self.sort_key.lock().unwrap();
+                    let key = SortKey::cycle(*sort_key);
+                    sort_key.replace(key);
+                    key
+                };
+                let _ = self.oper_sender.try_send(Operation::Sort(key));
+            }
+            "nvim_preview_event" => {
+                // info!("handle_notify: name: {}, args: {:?}", name, args);
+                let _ = self.oper_sender.try_send(Operation::PreviewExtract);
+            }
+            "nvim_toggle_flatten_event" => {
+                // info!("handle_notify: name: {}, args: {:?}", name, args);
+                let _ = self.oper_sender.try_send(Operation::ToggleFlatten);
+            }
+            "nvim_cursor_moved_event" => {
+                if let Some(line) = args.first().and_then(|a| a.as_str()) {
                     let _ = self
                         .oper_sender
-                        .try_send(Operation::SelectPassword(pwd.to_string()));
+                        .try_send(Operation::FileDetail(line.to_string()));
+                }
+            }
+            "nvim_formats_event" => {
+                let _ = self.oper_sender.try_send(Operation::ShowFormats);
+            }
+            "nvim_export_list_event" => {
+                if let Some(path) = args.first().and_then(|a| a.as_str()) {
+                    let path = path.trim();
+                    if !path.is_empty() {
+                        let _ = self
+                            .oper_sender
+                            .try_send(Operation::ExportList(PathBuf::from(path), false));
+                    }
+                }
+            }
+            "nvim_toggle_hidden_event" => {
+                let _ = self.oper_sender.try_send(Operation::ToggleHidden);
+            }
+            "nvim_volumes_event" => {
+                let _ = self.oper_sender.try_send(Operation::ShowVolumes);
+            }
+            "nvim_toggle_wrap_dir_event" => {
+                let _ = self.oper_sender.try_send(Operation::ToggleWrapDir);
+            }
+            "nvim_overwrite_event" => {
+                // info!("handle_notify: name: {}, args: {:?}", name, args);
+                let mode = {
+                    let mut overwrite = self.overwrite.lock().unwrap();
+                    *overwrite = overwrite.cycle();
+                    *overwrite
+                };
+                let _ = self.oper_sender.try_send(Operation::SetOverwriteMode(mode));
+            }
+            "nvim_open_dest_event" => {
+                // info!("handle_notify: name: {}, args: {:?}", name, args);
+                let _ = self.oper_sender.try_send(Operation::OpenDestination);
+            }
+            "nvim_technical_list_event" => {
+                // info!("handle_notify: name: {}, args: {:?}", name, args);
+                let _ = self.oper_sender.try_send(Operation::TechnicalList);
+            }
+            "nvim_enter_archive_event" => {
+                if let Some(line) = args.first().and_then(|a| a.as_str()) {
+                    let _ = self
+                        .oper_sender
+                        .try_send(Operation::EnterArchive(line.to_string()));
+                }
+            }
+            "nvim_leave_archive_event" => {
+                let _ = self.oper_sender.try_send(Operation::LeaveArchive);
+            }
+            "nvim_refresh_event" => {
+                let _ = self.oper_sender.try_send(Operation::Refresh);
+            }
+            "nvim_cancel_event" => {
+                let _ = self.oper_sender.try_send(Operation::Cancel);
+            }
+            "nvim_checksum_event" => {
+                // the choice of confirm(), 0 when cancelled
+                let kind = match args.first().and_then(|a| a.as_i64()) {
+                    Some(1) => HashKind::Crc32,
+                    Some(2) => HashKind::Sha256,
+                    _ => return,
+                };
+                let _ = self
+                    .oper_sender
+                    .try_send(Operation::ChecksumExtracted(kind));
+            }
+            "nvim_clear_history_event" => {
+                // info!("handle_notify: name: {}, args: {:?}", name, args);
+                let _ = self.oper_sender.try_send(Operation::ClearHistory);
+            }
+            "nvim_quit_event" => {
+                // info!("handle_notify: name: {}, args: {:?}", name, args);
+                let _ = self.oper_sender.try_send(Operation::Quit);
+            }
+            "nvim_test_event" => {
+                // info!("handle_notify: name: {}, args: {:?}", name, args);
+                let _ = self.oper_sender.try_send(Operation::Test);
+            }
+            "nvim_select_password_event" => {
+                info!("handle_notify: name: {}, args: {:?}", name, args);
+                match args.first().and_then(|a| a.as_str()) {
+                    Some(pwd) => {
+                        let _ = self
+                            .oper_sender
+                            .try_send(Operation::SelectPassword(pwd.to_string()));
+                    }
+                    None => warn!("malformed {} notification: {:?}", name, args),
                 }
             }
             "nvim_retry_event" => {
@@ -147,7 +534,428 @@ impl Handler for NeovimHandler {
     }
 }
 
-const HIGHLIGHT_ERROR_GROUP: &str = "DiagnosticError";
+const HIGHLIGHT_HEADER_GROUP: &str = "Title";
+const HIGHLIGHT_SEPARATOR_GROUP: &str = "Comment";
+const HIGHLIGHT_SUMMARY_GROUP: &str = "Identifier";
+const HIGHLIGHT_PASSWORD_GROUP: &str = "Special";
+// highlights are added in it, then cleared all at once on the next full push
+const HIGHLIGHT_NAMESPACE: &str = "vui-7z";
+
+/// (group, line index) to highlight in the pushed lines,
+/// the error and warning groups are from the options
+fn highlights<'a>(
+    lines: &[String],
+    error_group: &'a str,
+    warning_group: &'a str,
+) -> Vec<(&'a str, usize)> {
+    let mut highlights = vec![];
+    let mut dash_lines = 0;
+    for (i, line) in lines.iter().enumerate() {
+        if line.starts_with("ERROR:") {
+            highlights.push((error_group, i));
+        } else if line.starts_with("WARNING") {
+            highlights.push((warning_group, i));
+        } else if line.starts_with("Enter password: ") || line.starts_with("select password use") {
+            // the input line and the choices from history
+            highlights.push((HIGHLIGHT_PASSWORD_GROUP, i));
+        } else if line.starts_with("-----") {
+            dash_lines += 1;
+            highlights.push((HIGHLIGHT_SEPARATOR_GROUP, i));
+        } else if dash_lines == 0 && line.contains("Attr") && line.contains("Name") {
+            highlights.push((HIGHLIGHT_HEADER_GROUP, i));
+        } else if dash_lines == 2 {
+            // the summary is right after the closing dash line
+            dash_lines += 1;
+            highlights.push((HIGHLIGHT_SUMMARY_GROUP, i));
+        }
+    }
+    highlights
+}
+
+/// the path starts right after "Extract to: ", other lines keep a normal <Tab>,
+/// the copied path is resolved by "nvim_file_path", nothing is copied from other lines
+const VIM_SCRIPT: &str = r#"
+function! Vui7zCopyPath()
+  let path = rpcrequest(g:vui7z_channel, 'nvim_file_path', getline('.'))
+  if path !=# ''
+    call setreg('+', path)
+    echo 'copied: ' . path
+  endif
+endfunction
+function! Vui7zCompletePath(findstart, base)
+  if a:findstart
+    return getline('.') =~# '^Extract to: ' ? len('Extract to: ') : -3
+  endif
+  return rpcrequest(g:vui7z_channel, 'nvim_complete_path', a:base)
+endfunction
+set completefunc=Vui7zCompletePath
+inoremap <expr> <Tab> getline('.') =~# '^Extract to: ' ? "\<C-x>\<C-u>" : "\<Tab>"
+"#;
+
+/// (action, mode, default key, rhs, event to subscribe)
+/// the rpcnotify event names are stable, only the keys can be remapped
+const KEYMAPS: [(&str, &str, &str, &str, Option<&str>); 32] = [
+    // nvim will notify "nvim_execute_event" to handler
+    (
+        "execute",
+        "n",
+        "<space>c",
+        r#":call rpcnotify(0, "nvim_execute_event")<CR>"#,
+        Some("nvim_execute_event"),
+    ),
+    // visual keymap, selected lines will be sent with "nvim_extract_files_event"
+    (
+        "extract_files",
+        "x",
+        "<space>e",
+        r#":<C-u>call rpcnotify(0, "nvim_extract_files_event", getline("'<", "'>"))<CR>"#,
+        Some("nvim_extract_files_event"),
+    ),
+    // nvim will notify "nvim_peek_event" with no lines, the whole archive goes to a temp dir
+    (
+        "peek",
+        "n",
+        "<space>P",
+        r#":call rpcnotify(0, "nvim_peek_event", [])<CR>"#,
+        Some("nvim_peek_event"),
+    ),
+    // the selected lines to "nvim_peek_event"
+    (
+        "peek_files",
+        "x",
+        "<space>P",
+        r#":<C-u>call rpcnotify(0, "nvim_peek_event", getline("'<", "'>"))<CR>"#,
+        Some("nvim_peek_event"),
+    ),
+    // prompt a path with file completion then notify "nvim_add_event"
+    (
+        "add",
+        "n",
+        "<space>a",
+        r#":call rpcnotify(0, "nvim_add_event", input("Add file: ", "", "file"))<CR>"#,
+        Some("nvim_add_event"),
+    ),
+    // same as "add", then prompt a hidden password, an empty one encrypts nothing
+    (
+        "add_encrypted",
+        "n",
+        "<space>A",
+        r#":call rpcnotify(0, "nvim_add_encrypted_event", input("Add file: ", "", "file"), inputsecret("Encrypt with password: "))<CR>"#,
+        Some("nvim_add_encrypted_event"),
+    ),
+    // visual keymap, "nvim_delete_event" fires only after confirmed
+    (
+        "delete",
+        "x",
+        "<space>D",
+        r#":<C-u>if confirm("Delete selected files from archive?", "&Yes\n&No", 2) == 1 <Bar> call rpcnotify(0, "nvim_delete_event", getline("'<", "'>")) <Bar> endif<CR>"#,
+        Some("nvim_delete_event"),
+    ),
+    // nvim will notify "nvim_sort_event" to handler
+    (
+        "sort",
+        "n",
+        "<space>s",
+        r#":call rpcnotify(0, "nvim_sort_event")<CR>"#,
+        Some("nvim_sort_event"),
+    ),
+    // prompt a pattern then notify "nvim_filter_event", empty input clears the filter
+    (
+        "filter",
+        "n",
+        "<space>/",
+        r#":call rpcnotify(0, "nvim_filter_event", input("Filter: "))<CR>"#,
+        Some("nvim_filter_event"),
+    ),
+    // prompt a query then notify "nvim_find_event" with the cursor row to search after
+    (
+        "find",
+        "n",
+        "<space>n",
+        r#":call rpcnotify(0, "nvim_find_event", input("Find file: "), line("."))<CR>"#,
+        Some("nvim_find_event"),
+    ),
+    // nvim will notify "nvim_preview_event" to handler
+    (
+        "preview",
+        "n",
+        "<space>p",
+        r#":call rpcnotify(0, "nvim_preview_event")<CR>"#,
+        Some("nvim_preview_event"),
+    ),
+    // nvim will notify "nvim_toggle_flatten_event" to handler
+    (
+        "toggle_flatten",
+        "n",
+        "<space>f",
+        r#":call rpcnotify(0, "nvim_toggle_flatten_event")<CR>"#,
+        Some("nvim_toggle_flatten_event"),
+    ),
+    // add or remove the archive name subdirectory on "Extract to: "
+    (
+        "toggle_wrap_dir",
+        "n",
+        "<space>w",
+        r#":call rpcnotify(0, "nvim_toggle_wrap_dir_event")<CR>"#,
+        Some("nvim_toggle_wrap_dir_event"),
+    ),
+    // nvim will notify "nvim_overwrite_event" to handler
+    (
+        "cycle_overwrite",
+        "n",
+        "<space>o",
+        r#":call rpcnotify(0, "nvim_overwrite_event")<CR>"#,
+        Some("nvim_overwrite_event"),
+    ),
+    // nvim will notify "nvim_open_dest_event" to handler, "<space>o" is taken by overwrite
+    (
+        "open_destination",
+        "n",
+        "<space>O",
+        r#":call rpcnotify(0, "nvim_open_dest_event")<CR>"#,
+        Some("nvim_open_dest_event"),
+    ),
+    // pick the hash then notify "nvim_checksum_event" with the choice
+    (
+        "checksum_extracted",
+        "n",
+        "<space>k",
+        r#":call rpcnotify(0, "nvim_checksum_event", confirm("Checksum of the extracted files", "&CRC32\n&SHA-256", 1))<CR>"#,
+        Some("nvim_checksum_event"),
+    ),
+    // the line under the cursor to "nvim_enter_archive_event", a nested archive is listed
+    (
+        "enter_archive",
+        "n",
+        "<space>g",
+        r#":call rpcnotify(0, "nvim_enter_archive_event", getline("."))<CR>"#,
+        Some("nvim_enter_archive_event"),
+    ),
+    // nvim will notify "nvim_leave_archive_event" to handler
+    (
+        "leave_archive",
+        "n",
+        "<space>b",
+        r#":call rpcnotify(0, "nvim_leave_archive_event")<CR>"#,
+        Some("nvim_leave_archive_event"),
+    ),
+    // nvim will notify "nvim_technical_list_event" to handler
+    (
+        "technical_list",
+        "n",
+        "<space>l",
+        r#":call rpcnotify(0, "nvim_technical_list_event")<CR>"#,
+        Some("nvim_technical_list_event"),
+    ),
+    // nvim will notify "nvim_formats_event" to handler
+    (
+        "show_formats",
+        "n",
+        "<space>i",
+        r#":call rpcnotify(0, "nvim_formats_event")<CR>"#,
+        Some("nvim_formats_event"),
+    ),
+    // prompt a file with completion then notify "nvim_export_list_event"
+    (
+        "export_list",
+        "n",
+        "<space>E",
+        r#":call rpcnotify(0, "nvim_export_list_event", input("Export list to: ", "", "file"))<CR>"#,
+        Some("nvim_export_list_event"),
+    ),
+    // nvim will notify "nvim_toggle_hidden_event" to handler
+    (
+        "toggle_hidden",
+        "n",
+        "<space>h",
+        r#":call rpcnotify(0, "nvim_toggle_hidden_event")<CR>"#,
+        Some("nvim_toggle_hidden_event"),
+    ),
+    // nvim will notify "nvim_volumes_event" to handler
+    (
+        "show_volumes",
+        "n",
+        "<space>v",
+        r#":call rpcnotify(0, "nvim_volumes_event")<CR>"#,
+        Some("nvim_volumes_event"),
+    ),
+    // "nvim_clear_history_event" fires only after confirmed
+    (
+        "clear_history",
+        "n",
+        "<space>d",
+        r#":if confirm("Clear saved password history?", "&Yes\n&No", 2) == 1 <Bar> call rpcnotify(0, "nvim_clear_history_event") <Bar> endif<CR>"#,
+        Some("nvim_clear_history_event"),
+    ),
+    // nvim will notify "nvim_test_event" to handler
+    (
+        "test",
+        "n",
+        "<space>t",
+        r#":call rpcnotify(0, "nvim_test_event")<CR>"#,
+        Some("nvim_test_event"),
+    ),
+    // nvim will notify "nvim_cancel_event" to handler
+    (
+        "cancel",
+        "n",
+        "<space>C",
+        r#":call rpcnotify(0, "nvim_cancel_event")<CR>"#,
+        Some("nvim_cancel_event"),
+    ),
+    // nvim will notify "nvim_retry_event" to handler
+    (
+        "retry",
+        "n",
+        "<space>r",
+        r#":call rpcnotify(0, "nvim_retry_event")<CR>"#,
+        Some("nvim_retry_event"),
+    ),
+    // nvim will notify "nvim_refresh_event" to handler, the password is kept
+    (
+        "refresh",
+        "n",
+        "<space>R",
+        r#":call rpcnotify(0, "nvim_refresh_event")<CR>"#,
+        Some("nvim_refresh_event"),
+    ),
+    // nvim will quit
+    ("quit", "n", "<space>q", r#":qa!<CR>"#, None),
+    // quit after the running 7z command finished
+    (
+        "quit_after_finished",
+        "n",
+        "<space>Q",
+        r#":call rpcnotify(0, "nvim_quit_event")<CR>"#,
+        Some("nvim_quit_event"),
+    ),
+    // the archive path of the file under the cursor to the "+" register, by rpcrequest
+    (
+        "copy_path",
+        "n",
+        "<space>y",
+        r#":call Vui7zCopyPath()<CR>"#,
+        None,
+    ),
+    // yank the numbered password choice between [] and notify "nvim_select_password_event"
+    (
+        "select_password",
+        "n",
+        "<space>x",
+        r#"yi]:call rpcnotify(0, "nvim_select_password_event", getreg(0))<CR>"#,
+        Some("nvim_select_password_event"),
+    ),
+];
+
+/// the buffer may be shorter now, keep the saved view rows inside it
+fn clamp_view(view: Value, line_count: i64) -> Value {
+    let Value::Map(entries) = view else {
+        return view;
+    };
+    let entries = entries
+        .into_iter()
+        .map(|(k, v)| match (k.as_str(), v.as_i64()) {
+            (Some("lnum" | "topline"), Some(row)) => {
+                let row = row.clamp(1, line_count.max(1));
+                (k, Value::from(row))
+            }
+            _ => (k, v),
+        })
+        .collect();
+    Value::Map(entries)
+}
+
+pub struct Keymap {
+    action: &'static str,
+    mode: &'static str,
+    key: String,
+    rhs: &'static str,
+    event: Option<&'static str>,
+}
+
+/// the default keymaps, with the keys in overrides replaced by action name
+pub fn keymaps(overrides: &HashMap<String, String>) -> Result<Vec<Keymap>, String> {
+    if let Some(action) = overrides
+        .keys()
+        .find(|action| !KEYMAPS.iter().any(|(name, ..)| name == action))
+    {
+        return Err(format!("unknown keymap action: {}", action));
+    }
+    let mut bound: HashMap<(&str, &str), &str> = HashMap::new();
+    let mut keymaps = Vec::new();
+    for (action, mode, key, rhs, event) in KEYMAPS {
+        let key = overrides.get(action).map(String::as_str).unwrap_or(key);
+        if let Some(other) = bound.insert((mode, key), action) {
+            return Err(format!(
+                "keymap {} is bound to both {} and {}",
+                key, other, action
+            ));
+        }
+        keymaps.push(Keymap {
+            action,
+            mode,
+            key: key.to_string(),
+            rhs,
+            event,
+        });
+    }
+    Ok(keymaps)
+}
+
+/// how a key is shown in the hints, "<space>c" is "space+c" and "<C-x>" is "Ctrl+x"
+fn key_label(key: &str) -> String {
+    let mut parts = vec![];
+    let mut rest = key;
+    while !rest.is_empty() {
+        if let Some((special, after)) = rest.strip_prefix('<').and_then(|r| r.split_once('>')) {
+            let mut names = special.split('-').collect::<Vec<_>>();
+            let name = names.pop().unwrap_or_default();
+            for modifier in names {
+                parts.push(match modifier.to_ascii_uppercase().as_str() {
+                    "C" => "Ctrl".to_string(),
+                    "S" => "Shift".to_string(),
+                    "M" | "A" => "Alt".to_string(),
+                    other => other.to_string(),
+                });
+            }
+            parts.push(name.to_lowercase());
+            rest = after;
+        } else {
+            let end = rest.find('<').unwrap_or(rest.len()).max(1);
+            parts.push(rest[..end].to_string());
+            rest = &rest[end..];
+        }
+    }
+    parts.join("+")
+}
+
+/// action => key label, remapped keys included
+pub fn key_labels(keymaps: &[Keymap]) -> HashMap<String, String> {
+    keymaps
+        .iter()
+        .map(|k| (k.action.to_string(), key_label(&k.key)))
+        .collect()
+}
+
+/// only a socket is removed, --socket may point at any file by mistake
+fn remove_stale_socket(path: &Path) -> std::io::Result<()> {
+    let metadata = match std::fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    };
+    if metadata.file_type().is_socket() {
+        std::fs::remove_file(path)
+    } else {
+        Err(std::io::Error::new(
+            ErrorKind::AlreadyExists,
+            format!(
+                "--socket path exists and is not a socket: {}",
+                path.display()
+            ),
+        ))
+    }
+}
 
 pub struct Nvim;
 
@@ -155,29 +963,84 @@ impl Nvim {
     pub async fn start(
         mut doc_recv: mpsc::Receiver<Pushment>,
         oper_sender: mpsc::Sender<Operation>,
+        opt: &Options,
+        keymaps: Vec<Keymap>,
     ) -> tokio::io::Result<()> {
-        if let Err(e) = Command::new(NVIMPATH)
-            .args(["-u", "NONE", "--listen", "/tmp/nvim-socket-001"])
+        let socket = opt.socket_path();
+        let path = socket.as_path();
+        // a stale socket would be taken as nvim is ready
+        if let Err(e) = remove_stale_socket(path) {
+            error!("{}", e);
+            return Err(e);
+        }
+        let mut command = Command::new(&opt.nvim_bin);
+        // the archive came from a pipe, nvim still needs the terminal
+        if !std::io::stdin().is_terminal() {
+            match std::fs::File::open("/dev/tty") {
+                Ok(tty) => {
+                    command.stdin(tty);
+                }
+                Err(e) => error!("open /dev/tty for nvim error: {}", e),
+            }
+        }
+        let mut child = match command
+            .args(["-u", "NONE", "--listen"])
+            .arg(path)
             .stdout(stdout())
+            .stderr(Stdio::piped())
             .spawn()
         {
-            error!("Failed to start nvim: {}", e);
-            return Err(e)?;
+            Ok(child) => child,
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                error!(
+                    "nvim binary not found: {}, try --nvim to specify it",
+                    opt.nvim_bin
+                );
+                return Err(std::io::Error::new(
+                    ErrorKind::NotFound,
+                    format!("nvim binary not found: {}", opt.nvim_bin),
+                ));
+            }
+            Err(e) => {
+                error!("Failed to start nvim: {}", e);
+                return Err(e)?;
+            }
+        };
+        // nvim stderr goes to the log, so a startup failure is diagnosable
+        if let Some(stderr) = child.stderr.take() {
+            tokio::spawn(async move {
+                let mut lines = BufReader::new(stderr).lines();
+                while let Ok(Some(line)) = lines.next_line().await {
+                    error!("nvim stderr: {}", line);
+                }
+            });
         }
-        let path = Path::new("/tmp/nvim-socket-001");
-        // wait for /tmp/nvim-socket-001 to be created
+        // wait for the socket to be created, give up if nvim exited or timeout
+        let timeout = Duration::from_secs(opt.socket_timeout);
+        let begin = Instant::now();
         while !path.exists() {
+            if let Some(status) = child.try_wait()? {
+                error!("nvim exited before socket created: {}", status);
+                return Err(ErrorKind::BrokenPipe.into());
+            }
+            if begin.elapsed() > timeout {
+                error!("nvim socket not created in {:?}: {:?}", timeout, path);
+                let _ = child.kill().await;
+                return Err(ErrorKind::TimedOut.into());
+            }
             sleep(Duration::from_millis(10)).await;
         }
 
         // clone oper_sender to NeovimHandler, it will drop when nvim quit, i want keep it alive;
         let oper_sender_ = oper_sender.clone();
-        let handler = NeovimHandler::new(oper_sender_);
+        let handler = NeovimHandler::new(oper_sender_, opt.file.dir());
+        // pushed "Extract to: " lines are known to the handler, they are not edits
+        let last_extract_to = handler.last_extract_to.clone();
         let (nvim, io_handle) = new_path(path, handler)
             .await
             .expect("connect to nvim failed");
 
-        Self::initialize_nvim(&nvim)
+        Self::initialize_nvim(&nvim, &keymaps)
             .await
             .expect("initialize nvim error");
 
@@ -188,22 +1051,61 @@ impl Nvim {
             .await
             .expect("attach buf error");
 
+        let detail = match opt.detail_pane {
+            true => Some(
+                Self::open_detail_pane(&nvim, &curbuf)
+                    .await
+                    .expect("open detail pane error"),
+            ),
+            false => None,
+        };
+
+        let namespace = nvim
+            .create_namespace(HIGHLIGHT_NAMESPACE)
+            .await
+            .expect("create namespace error");
+
+        let error_group = opt.error_highlight.clone();
+        let warning_group = opt.warning_highlight.clone();
         // receive pushment from 7z, then push to nvim
         let wait_push = async move {
             while let Some(pushment) = doc_recv.recv().await {
                 match pushment {
                     Pushment::Full(lines, cursor) => {
                         // info!("recv pushment: {:?}", lines);
-                        let err_line = lines
-                            .iter()
-                            .enumerate()
-                            .find(|l| l.1.starts_with("ERROR:"))
-                            .map(|(col, _)| col);
+                        let highlights = highlights(&lines, &error_group, &warning_group);
+                        let password_prompt = cursor.is_some_and(|(line, _)| {
+                            lines
+                                .get(line.saturating_sub(1))
+                                .is_some_and(|l| l.starts_with("Enter password"))
+                        });
                         let line_count = curbuf.line_count().await.expect("get line count error");
+                        // set_lines resets the view, keep where the user was looking
+                        let view = match cursor {
+                            Some(_) => None,
+                            None => nvim.call_function("winsaveview", vec![]).await.ok(),
+                        };
+                        let new_count = lines.len() as i64;
+                        if let Some(path) = lines.iter().find_map(|l| extract_to_value(l)) {
+                            last_extract_to
+                                .lock()
+                                .unwrap()
+                                .changed(path, Instant::now());
+                        }
                         let _ = curbuf.set_lines(0, line_count, false, lines).await;
-                        if let Some(err_col) = err_line {
+                        if let Some(view) = view {
+                            let view = clamp_view(view, new_count);
+                            if let Err(e) = nvim.call_function("winrestview", vec![view]).await {
+                                error!("restore view error: {}", e);
+                            }
+                        }
+                        curbuf
+                            .clear_namespace(namespace, 0, -1)
+                            .await
+                            .expect("clear highlight error");
+                        for (group, line) in highlights {
                             curbuf
-                                .add_highlight(-1, HIGHLIGHT_ERROR_GROUP, err_col as i64, 0, -1)
+                                .add_highlight(namespace, group, line as i64, 0, -1)
                                 .await
                                 .expect("add highlight error");
                         }
@@ -212,16 +1114,34 @@ impl Nvim {
                             win.set_cursor((col as i64, row as i64))
                                 .await
                                 .expect("set cursor error");
-                            let _ = nvim
-                                .call("nvim_command", vec!["startinsert!".into()])
-                                .await
-                                .expect("start insert error");
+                            // type the password right away, a found file is only jumped to
+                            if password_prompt {
+                                let _ = nvim
+                                    .call("nvim_command", vec!["startinsert!".into()])
+                                    .await
+                                    .expect("start insert error");
+                            }
                         }
                     }
-                    Pushment::Line(line, content) => curbuf
-                        .set_lines(line as i64, line as i64, false, vec![content])
-                        .await
-                        .expect("set lines error"),
+                    Pushment::Detail(lines) => {
+                        if let Some(detail) = detail.as_ref() {
+                            if let Err(e) = detail.set_lines(0, -1, false, lines).await {
+                                error!("set detail lines error: {}", e);
+                            }
+                        }
+                    }
+                    Pushment::Line(line, content) => {
+                        if let Some(path) = extract_to_value(&content) {
+                            last_extract_to
+                                .lock()
+                                .unwrap()
+                                .changed(path, Instant::now());
+                        }
+                        curbuf
+                            .set_lines(line as i64 - 1, line as i64, false, vec![content])
+                            .await
+                            .expect("set lines error")
+                    }
                     Pushment::None => {
                         nvim.quit_no_save().await.expect("quit nvim error");
                     }
@@ -249,11 +1169,42 @@ impl Nvim {
 
         let _ = try_join!(wait_push, wait_io);
         info!("nvim quit");
+        if path.exists() {
+            let _ = std::fs::remove_file(path);
+        }
         Ok(())
     }
 
+    /// a scratch buffer on the right for the file under the cursor,
+    /// moving in the main buffer notifies "nvim_cursor_moved_event" with the line
+    async fn open_detail_pane(
+        nvim: &Neovim<Compat<WriteHalf<Connection>>>,
+        main: &Buffer<Compat<WriteHalf<Connection>>>,
+    ) -> Result<Buffer<Compat<WriteHalf<Connection>>>, Box<CallError>> {
+        nvim.command("botright vertical 60new").await?;
+        nvim.command("setlocal buftype=nofile bufhidden=wipe noswapfile nobuflisted")
+            .await?;
+        let detail = nvim.get_current_buf().await?;
+        // the focus goes back to the file list
+        nvim.command("wincmd p").await?;
+        nvim.create_autocmd(
+            Value::Array(vec!["CursorMoved".into()]),
+            vec![
+                ("buffer".into(), main.get_number().await?.into()),
+                (
+                    "command".into(),
+                    r#"call rpcnotify(0, "nvim_cursor_moved_event", getline("."))"#.into(),
+                ),
+            ],
+        )
+        .await?;
+        nvim.subscribe("nvim_cursor_moved_event").await?;
+        Ok(detail)
+    }
+
     async fn initialize_nvim(
         nvim: &Neovim<Compat<WriteHalf<Connection>>>,
+        keymaps: &[Keymap],
     ) -> Result<(), Box<CallError>> {
         // register "nvim_insert_leave_event", then subscribe it
         // nvim_insert_leave_event has been triggered, then check password from buf line, then send password to 7z
@@ -269,44 +1220,189 @@ impl Nvim {
         .await?;
         nvim.subscribe("nvim_insert_leave_event").await?;
 
-        // register keymap "<space>c" to nvim, then nvim will notify "nvim_execute_event" to handler
-        nvim.set_keymap(
-            "n",
-            "<space>c",
-            r#":call rpcnotify(0, "nvim_execute_event")<CR>"#,
-            vec![("silent".into(), true.into())],
-        )
-        .await?;
-        nvim.subscribe("nvim_execute_event").await?;
-
-        // register keymap "<space>r" to nvim, then nvim will notify "nvim_retry_event" to handler
-        nvim.set_keymap(
-            "n",
-            "<space>r",
-            r#":call rpcnotify(0, "nvim_retry_event")<CR>"#,
-            vec![("silent".into(), true.into())],
-        )
-        .await?;
-        nvim.subscribe("nvim_retry_event").await?;
-
-        // register keymap "<space>q" to nvim, then nvim will quit
-        nvim.set_keymap(
-            "n",
-            "<space>q",
-            r#":qa!<CR>"#,
-            vec![("silent".into(), true.into())],
-        )
-        .await?;
+        // every file of the technical list is a fold
+        nvim.command("set foldmethod=marker").await?;
 
-        // register keymap "<space>x" to nvim
-        nvim.set_keymap(
-            "n",
-            "<space>x",
-            r#"yi]:call rpcnotify(0, "nvim_select_password_event", getreg(0))<CR>"#,
-            vec![("silent".into(), true.into())],
-        )
-        .await?;
-        nvim.subscribe("nvim_select_password_event").await?;
+        // <Tab> on the "Extract to: " line completes the path by "nvim_complete_path",
+        // copy_path calls back with "nvim_file_path"
+        let channel = nvim.get_api_info().await?.swap_remove(0);
+        nvim.set_var("vui7z_channel", channel).await?;
+        nvim.exec(VIM_SCRIPT, false).await?;
+
+        for keymap in keymaps {
+            nvim.set_keymap(
+                keymap.mode,
+                &keymap.key,
+                keymap.rhs,
+                vec![("silent".into(), true.into())],
+            )
+            .await?;
+            if let Some(event) = keymap.event {
+                nvim.subscribe(event).await?;
+            }
+        }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::{collections::HashMap, time::Duration};
+
+    use nvim_rs::Value;
+    use tokio::time::Instant;
+
+    use super::{
+        clamp_view, extract_to_value, highlights, key_label, key_labels, keymaps,
+        remove_stale_socket, BufLineChanges, CursorAt, LastSent, DEBOUNCE, HIGHLIGHT_HEADER_GROUP,
+        HIGHLIGHT_PASSWORD_GROUP, HIGHLIGHT_SEPARATOR_GROUP, HIGHLIGHT_SUMMARY_GROUP,
+    };
+
+    #[test]
+    fn test_highlights() {
+        let lines = [
+            "7Z-VUI",
+            "Enter password: ",
+            "select password use [space+x]: [1: 123456]",
+            "   Date      Time    Attr         Size   Compressed  Name",
+            "------------------- ----- ------------ ------------  ------------------------",
+            "2023-12-12 09:18:24 ....A       344963     13216256  test/Name-Attr.png",
+            "------------------- ----- ------------ ------------  ------------------------",
+            "2023-12-22 16:17:58           13338079     13216256  22 files, 1 folders",
+            "WARNING: Cannot open file a.png",
+            "ERROR: Wrong password",
+            "ERROR: Data Error : test/01-e_01.png",
+        ]
+        .map(String::from);
+        assert_eq!(
+            highlights(&lines, "DiagnosticError", "WarningMsg"),
+            vec![
+                (HIGHLIGHT_PASSWORD_GROUP, 1),
+                (HIGHLIGHT_PASSWORD_GROUP, 2),
+                (HIGHLIGHT_HEADER_GROUP, 3),
+                (HIGHLIGHT_SEPARATOR_GROUP, 4),
+                (HIGHLIGHT_SEPARATOR_GROUP, 6),
+                (HIGHLIGHT_SUMMARY_GROUP, 7),
+                ("WarningMsg", 8),
+                ("DiagnosticError", 9),
+                ("DiagnosticError", 10),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_clamp_view() {
+        let view = Value::Map(vec![
+            ("lnum".into(), 120.into()),
+            ("topline".into(), 100.into()),
+            ("col".into(), 4.into()),
+        ]);
+        let Value::Map(view) = clamp_view(view, 50) else {
+            panic!("view is not a map");
+        };
+        assert_eq!(view[0].1.as_i64(), Some(50));
+        assert_eq!(view[1].1.as_i64(), Some(50));
+        assert_eq!(view[2].1.as_i64(), Some(4));
+    }
+
+    #[test]
+    fn test_keymaps() {
+        let defaults = keymaps(&HashMap::new()).unwrap();
+        assert!(defaults.iter().any(|k| k.key == "<space>c"));
+
+        let overrides = HashMap::from([("execute".to_string(), "<space>X".to_string())]);
+        let remapped = keymaps(&overrides).unwrap();
+        assert!(remapped.iter().any(|k| k.key == "<space>X"));
+        assert!(!remapped.iter().any(|k| k.key == "<space>c"));
+
+        // same key in different modes is fine
+        let overrides = HashMap::from([("extract_files".to_string(), "<space>c".to_string())]);
+        assert!(keymaps(&overrides).is_ok());
+
+        let overrides = HashMap::from([("retry".to_string(), "<space>c".to_string())]);
+        assert!(keymaps(&overrides).is_err());
+        let overrides = HashMap::from([("nope".to_string(), "<space>z".to_string())]);
+        assert!(keymaps(&overrides).is_err());
+    }
+
+    #[test]
+    fn test_remove_stale_socket() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket = dir.path().join("nvim.sock");
+        assert!(remove_stale_socket(&socket).is_ok());
+        let listener = std::os::unix::net::UnixListener::bind(&socket).unwrap();
+        drop(listener);
+        assert!(remove_stale_socket(&socket).is_ok());
+        assert!(!socket.exists());
+        // a file given by mistake is kept
+        let notes = dir.path().join("notes.txt");
+        std::fs::write(&notes, "keep me").unwrap();
+        assert!(remove_stale_socket(&notes).is_err());
+        assert_eq!(std::fs::read_to_string(&notes).unwrap(), "keep me");
+    }
+
+    #[test]
+    fn test_key_labels() {
+        assert_eq!(key_label("<space>c"), "space+c");
+        assert_eq!(key_label("<C-x>"), "Ctrl+x");
+        assert_eq!(key_label("<leader>gx"), "leader+gx");
+        assert_eq!(key_label("<Space><C-S-k>"), "space+Ctrl+Shift+k");
+
+        let overrides = HashMap::from([("execute".to_string(), "<C-e>".to_string())]);
+        let labels = key_labels(&keymaps(&overrides).unwrap());
+        assert_eq!(labels["execute"], "Ctrl+e");
+        assert_eq!(labels["select_password"], "space+x");
+    }
+
+    #[test]
+    fn test_last_sent() {
+        let mut last = LastSent::default();
+        let now = Instant::now();
+        assert!(last.update("secret", now));
+        assert!(!last.update("secret", now + Duration::from_millis(50)));
+        // dropped ones do not move the window
+        assert!(last.update("secret", now + DEBOUNCE));
+        assert!(last.update("other", now + DEBOUNCE));
+        assert!(last.update("secret", now + DEBOUNCE + Duration::from_millis(1)));
+        // a pushed path is known for good
+        let mut last = LastSent::default();
+        assert!(last.changed("/tmp", now));
+        assert!(!last.changed("/tmp", now + DEBOUNCE * 10));
+        assert!(last.changed("/tmp/test", now));
+        assert_eq!(extract_to_value("Extract to: /tmp "), Some("/tmp"));
+        assert_eq!(extract_to_value("Enter password: "), None);
+    }
+
+    #[test]
+    fn test_malformed_notify_args() {
+        let cursor = CursorAt::try_from(vec![Value::Array(vec![
+            Value::from("n"),
+            Value::Array(vec![Value::from(3), Value::from(0)]),
+        ])])
+        .unwrap();
+        assert_eq!(cursor.col, 3);
+        assert!(CursorAt::try_from(vec![]).is_err());
+        assert!(CursorAt::try_from(vec![Value::from("n")]).is_err());
+
+        let buf_line = BufLineChanges::try_from(vec![
+            Value::from(1),
+            Value::from(2),
+            Value::from(5),
+            Value::from(6),
+            Value::Array(vec![Value::from("Enter password: ")]),
+            Value::from(false),
+        ])
+        .unwrap();
+        assert_eq!(buf_line.line_start, 5);
+        assert_eq!(buf_line.content, vec!["Enter password: "]);
+        assert!(BufLineChanges::try_from(vec![Value::from(1)]).is_err());
+        assert!(BufLineChanges::try_from(vec![
+            Value::from(1),
+            Value::from(2),
+            Value::from(5),
+            Value::from(6),
+            Value::Array(vec![Value::from(7)]),
+        ])
+        .is_err());
+    }
+}
@@ -1,30 +1,337 @@
-use std::path::PathBuf;
+use std::{collections::HashMap, fs, io::ErrorKind, path::PathBuf};
 
-use clap::Parser;
+use clap::{parser::ValueSource, ArgMatches, CommandFactory, FromArgMatches, Parser};
+use log::LevelFilter;
+use serde::Deserialize;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 pub struct Options {
     /// Input file that is a archive file, It's Required;
+    /// "-" reads it from stdin, an http(s) URL is downloaded first
     pub file: FilePath,
+    /// config file in toml, flags given on the command line override it
+    #[arg(short = 'c', long = "config", default_value_t = default_config_file())]
+    pub config_file: String,
     /// password history file
     #[arg(short = 'p', long = "password-history", default_value_t = default_password_history_file())]
     pub password_history_file: String,
+    /// 7z binary, name in PATH or full path, e.g. 7zz, 7za, /usr/bin/7z
+    #[arg(short = 'b', long = "bin", default_value_t = String::from("7z"))]
+    pub seven_zip_bin: String,
+    /// extra switch passed to every 7z command, repeatable, e.g. --7z-arg=-mmt=4.
+    /// 7z reports a malformed one as an ERROR line in the buffer
+    #[arg(long = "7z-arg", allow_hyphen_values = true)]
+    pub seven_zip_args: Vec<String>,
+    /// do not pass -scsUTF-8 to list and extract, for a 7z that rejects it
+    #[arg(long = "no-force-utf8")]
+    pub no_force_utf8: bool,
+    /// ask for a second execute within a few seconds before extracting
+    #[arg(long = "confirm-execute")]
+    pub confirm_execute: bool,
+    /// nvim binary, name in PATH or full path, e.g. nvim-nightly
+    #[arg(long = "nvim", default_value_t = String::from("nvim"))]
+    pub nvim_bin: String,
+    /// program to open the extract destination with, e.g. xdg-open, open, explorer
+    #[arg(long = "opener", default_value_t = default_opener())]
+    pub opener: String,
+    /// show an extra column with human-readable sizes (KiB/MiB/GiB) in the file list
+    #[arg(long = "human-size")]
+    pub human_size: bool,
+    /// shorten file names longer than it in the middle, in terminal columns, 0 means no limit
+    #[arg(long = "max-name-width", default_value_t = 0)]
+    pub max_name_width: usize,
+    /// open a pane on the right with the properties of the file under the cursor
+    #[arg(long = "detail-pane")]
+    pub detail_pane: bool,
+    /// do not remember the last extract directory across runs
+    #[arg(long = "no-remember-path")]
+    pub no_remember_path: bool,
+    /// extract to it instead of the archive directory, "~" and "$VAR" are expanded,
+    /// a relative one is from the working directory
+    #[arg(short = 'o', long = "output-dir")]
+    pub output_dir: Option<String>,
+    /// nvim highlight group of the "ERROR:" lines
+    #[arg(long = "error-highlight", default_value_t = String::from("DiagnosticError"))]
+    pub error_highlight: String,
+    /// nvim highlight group of the "WARNING" lines
+    #[arg(long = "warning-highlight", default_value_t = String::from("DiagnosticWarn"))]
+    pub warning_highlight: String,
+    /// show the password as asterisks in the buffer
+    #[arg(long = "mask-password")]
+    pub mask_password: bool,
+    /// max number of saved passwords, most recently used first, 0 means no limit
+    #[arg(long = "history-limit", default_value_t = 50)]
+    pub history_limit: usize,
+    /// wrong passwords in a row before suggesting the archive may be corrupt
+    #[arg(long = "max-password-attempts", default_value_t = 3)]
+    pub max_password_attempts: usize,
+    /// print the parsed listing to stdout without nvim, then quit
+    #[arg(long = "headless")]
+    pub headless: bool,
+    /// with --headless, write the parsed file list as JSON to the file, "-" is stdout
+    #[arg(long = "json", requires = "headless")]
+    pub json: Option<PathBuf>,
+    /// with --headless, write the file names one per line to the file, "-" is stdout
+    #[arg(long = "export-list", requires = "headless")]
+    pub export_list: Option<PathBuf>,
+    /// with --export-list, the extract destination follows each name after a tab
+    #[arg(long = "export-destinations", requires = "export_list")]
+    pub export_destinations: bool,
+    /// password for the archive, tried on the first attempt.
+    /// the flag is visible in the process list and shell history, prefer the env var
+    #[arg(
+        short = 'P',
+        long = "password",
+        env = "SEVENZ_VUI_PASSWORD",
+        hide_env_values = true
+    )]
+    pub password: Option<String>,
+    /// file of candidate passwords, one per line, tried in order when listing fails
+    #[arg(long = "try-passwords")]
+    pub try_passwords: Option<String>,
+    /// nvim listen socket, defaults to a per-process path in the temp dir
+    #[arg(long = "socket")]
+    pub socket: Option<String>,
+    /// seconds to wait for nvim to create the socket
+    #[arg(long = "socket-timeout", default_value_t = 5)]
+    pub socket_timeout: u64,
+    /// write the log to the file, no logging without it, e.g. ~/.config/7zvui/vui-7z.log
+    #[arg(long = "log-file")]
+    pub log_file: Option<PathBuf>,
+    /// max log level: off, error, warn, info, debug, trace
+    #[arg(long = "log-level", default_value_t = LevelFilter::Info)]
+    pub log_level: LevelFilter,
+    /// action name to key sequence, only from the config file
+    #[arg(skip)]
+    pub keymaps: HashMap<String, String>,
+}
+
+/// ~/.config/7zvui/config.toml
+/// ```toml
+/// password_history_file = "/path/to/password_history.txt"
+/// seven_zip_bin = "7zz"
+/// nvim_bin = "nvim"
+/// opener = "thunar"
+/// error_highlight = "ErrorMsg"
+/// warning_highlight = "WarningMsg"
+///
+/// [keymaps]
+/// execute = "<space>c"
+/// ```
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+struct Config {
+    password_history_file: Option<String>,
+    seven_zip_bin: Option<String>,
+    nvim_bin: Option<String>,
+    opener: Option<String>,
+    error_highlight: Option<String>,
+    warning_highlight: Option<String>,
+    keymaps: HashMap<String, String>,
+}
+
+impl Options {
+    /// parse the command line, then merge the config file into it
+    pub fn load() -> Result<Self, String> {
+        let matches = Options::command().get_matches();
+        let mut opt = Options::from_arg_matches(&matches).map_err(|e| e.to_string())?;
+        opt.load_config(&matches)?;
+        Ok(opt)
+    }
+
+    /// missing config file is fine, malformed one is an error
+    fn load_config(&mut self, matches: &ArgMatches) -> Result<(), String> {
+        let content = match fs::read_to_string(&self.config_file) {
+            Ok(content) => content,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(format!("can not read config {}: {}", self.config_file, e)),
+        };
+        let config = toml::from_str::<Config>(&content)
+            .map_err(|e| format!("malformed config {}: {}", self.config_file, e))?;
+        self.merge_config(config, matches);
+        Ok(())
+    }
+
+    fn merge_config(&mut self, config: Config, matches: &ArgMatches) {
+        // values from the command line or env var win
+        let from_default = |id: &str| {
+            !matches!(
+                matches.value_source(id),
+                Some(ValueSource::CommandLine) | Some(ValueSource::EnvVariable)
+            )
+        };
+        if let Some(v) = config.password_history_file {
+            if from_default("password_history_file") {
+                self.password_history_file = v;
+            }
+        }
+        if let Some(v) = config.seven_zip_bin {
+            if from_default("seven_zip_bin") {
+                self.seven_zip_bin = v;
+            }
+        }
+        if let Some(v) = config.nvim_bin {
+            if from_default("nvim_bin") {
+                self.nvim_bin = v;
+            }
+        }
+        if let Some(v) = config.opener {
+            if from_default("opener") {
+                self.opener = v;
+            }
+        }
+        if let Some(v) = config.error_highlight {
+            if from_default("error_highlight") {
+                self.error_highlight = v;
+            }
+        }
+        if let Some(v) = config.warning_highlight {
+            if from_default("warning_highlight") {
+                self.warning_highlight = v;
+            }
+        }
+        self.keymaps = config.keymaps;
+    }
+
+    pub fn socket_path(&self) -> PathBuf {
+        match &self.socket {
+            Some(socket) => PathBuf::from(socket),
+            None => std::env::temp_dir().join(format!("vui-7z-nvim-{}.sock", std::process::id())),
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
 pub struct FilePath {
     pub file: String,
+    // "-" or the URL, file is the temp copy after fetched
+    pub fetched_from: Option<String>,
 }
 
 impl From<String> for FilePath {
     fn from(file: String) -> Self {
+        if file == "-" || is_url(&file) {
+            return Self {
+                file: file.clone(),
+                fetched_from: Some(file),
+            };
+        }
         let mut current_dir = std::env::current_dir().unwrap();
         current_dir.push(file);
         Self {
             file: current_dir.to_str().unwrap().to_string(),
+            fetched_from: None,
+        }
+    }
+}
+
+fn is_url(file: &str) -> bool {
+    file.starts_with("http://") || file.starts_with("https://")
+}
+
+/// the temp directory of a fetched archive, removed on drop
+pub struct TempArchive {
+    dir: PathBuf,
+}
+
+impl Drop for TempArchive {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_dir_all(&self.dir) {
+            log::error!("remove temp archive {:?} error: {}", self.dir, e);
+        }
+    }
+}
+
+impl FilePath {
+    /// extract and complete relative paths from here, the temp directory does not last
+    pub fn dir(&self) -> PathBuf {
+        match self.fetched_from {
+            Some(_) => std::env::current_dir().unwrap(),
+            None => PathBuf::from(&self.file).parent().unwrap().to_path_buf(),
+        }
+    }
+
+    /// 7z needs a seekable file, copy stdin or download the URL to a temp file,
+    /// the status goes to stderr since nvim is not started yet
+    pub fn fetch(&mut self) -> Result<Option<TempArchive>, String> {
+        let Some(from) = self.fetched_from.clone() else {
+            return Ok(None);
+        };
+        let dir = std::env::temp_dir().join(format!("vui-7z-{}", std::process::id()));
+        fs::create_dir_all(&dir).map_err(|e| format!("can not create {:?}: {}", dir, e))?;
+        let temp = TempArchive { dir: dir.clone() };
+        let name = if from == "-" {
+            "stdin"
+        } else {
+            // the last segment keeps the extension, extract_to is named after it
+            from.split(['?', '#'])
+                .next()
+                .and_then(|url| url.rsplit('/').next())
+                .filter(|name| !name.is_empty())
+                .unwrap_or("download")
+        };
+        let path = dir.join(name);
+        if from == "-" {
+            eprintln!("reading archive from stdin ...");
+            let mut file =
+                fs::File::create(&path).map_err(|e| format!("can not create {:?}: {}", path, e))?;
+            let size = std::io::copy(&mut std::io::stdin().lock(), &mut file)
+                .map_err(|e| format!("read archive from stdin failed: {}", e))?;
+            eprintln!("read {} bytes from stdin", size);
+        } else {
+            eprintln!("downloading {} ...", from);
+            // curl draws its progress bar on stderr
+            let status = std::process::Command::new("curl")
+                .args(["-fL", "-#", "-o"])
+                .arg(&path)
+                .arg(&from)
+                .status()
+                .map_err(|e| format!("can not run curl to download {}: {}", from, e))?;
+            if !status.success() {
+                return Err(format!("download {} failed, curl {}", from, status));
+            }
         }
+        self.file = path.to_str().unwrap().to_string();
+        Ok(Some(temp))
     }
+
+    /// the archive must be a readable regular file
+    pub fn validate(&self) -> Result<(), String> {
+        let error = |e: std::io::Error| match e.kind() {
+            ErrorKind::NotFound => format!("archive not found: {}", self.file),
+            ErrorKind::PermissionDenied => format!("permission denied: {}", self.file),
+            _ => format!("can not open archive {}: {}", self.file, e),
+        };
+        let metadata = fs::metadata(&self.file).map_err(error)?;
+        if metadata.is_dir() {
+            return Err(format!("archive is a directory: {}", self.file));
+        }
+        if !metadata.is_file() {
+            return Err(format!("archive is not a regular file: {}", self.file));
+        }
+        fs::File::open(&self.file).map_err(error)?;
+        Ok(())
+    }
+}
+
+fn default_opener() -> String {
+    let opener = if cfg!(target_os = "macos") {
+        "open"
+    } else if cfg!(target_os = "windows") {
+        "explorer"
+    } else {
+        "xdg-open"
+    };
+    opener.to_string()
+}
+
+fn default_config_file() -> String {
+    let path = PathBuf::from(env!("HOME"))
+        .join(".config")
+        .join("7zvui")
+        .join("config.toml");
+    path.to_str().unwrap().to_string()
 }
 
 fn default_password_history_file() -> String {
@@ -35,3 +342,74 @@ fn default_password_history_file() -> String {
     // let path = PathBuf::from(env!("HOME")).join("code/vui-7z/config/password_history.txt");
     path.to_str().unwrap().to_string()
 }
+
+#[cfg(test)]
+mod test {
+    use clap::{CommandFactory, FromArgMatches};
+
+    use super::{Config, FilePath, Options};
+
+    #[test]
+    fn test_merge_config() {
+        let config = toml::from_str::<Config>(
+            r#"
+seven_zip_bin = "7zz"
+nvim_bin = "nvim-nightly"
+error_highlight = "ErrorMsg"
+[keymaps]
+execute = "<space>e"
+"#,
+        )
+        .unwrap();
+        let matches = Options::command()
+            .try_get_matches_from(["vui-7z", "test.7z", "--bin", "7za"])
+            .unwrap();
+        let mut opt = Options::from_arg_matches(&matches).unwrap();
+        opt.merge_config(config, &matches);
+        // command line wins
+        assert_eq!(opt.seven_zip_bin, "7za");
+        assert_eq!(opt.nvim_bin, "nvim-nightly");
+        assert_eq!(opt.keymaps["execute"], "<space>e");
+        assert_eq!(opt.error_highlight, "ErrorMsg");
+        assert_eq!(opt.warning_highlight, "DiagnosticWarn");
+
+        let err = toml::from_str::<Config>("seven_zip = 1").unwrap_err();
+        assert!(err.to_string().contains("unknown field"));
+    }
+
+    #[test]
+    fn test_fetched_file_path() {
+        let file = FilePath::from("https://example.com/a/test.7z?x=1".to_string());
+        assert_eq!(file.fetched_from.as_deref(), Some(file.file.as_str()));
+        assert_eq!(file.dir(), std::env::current_dir().unwrap());
+        let file = FilePath::from("-".to_string());
+        assert_eq!(file.file, "-");
+        let file = FilePath::from("test.7z".to_string());
+        assert!(file.fetched_from.is_none());
+        assert_eq!(file.dir(), std::env::current_dir().unwrap());
+        let mut file = FilePath::from("/tmp/test.7z".to_string());
+        assert_eq!(file.dir(), std::path::PathBuf::from("/tmp"));
+        assert!(file.fetch().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_validate_file_path() {
+        let dir = std::env::temp_dir();
+        let file = FilePath::from(dir.to_str().unwrap().to_string());
+        assert!(file
+            .validate()
+            .unwrap_err()
+            .starts_with("archive is a directory"));
+
+        let path = dir.join("vui-7z-test-not-exists.7z");
+        let file = FilePath::from(path.to_str().unwrap().to_string());
+        assert!(file
+            .validate()
+            .unwrap_err()
+            .starts_with("archive not found"));
+
+        std::fs::write(&path, "").unwrap();
+        assert!(file.validate().is_ok());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
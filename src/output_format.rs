@@ -1,7 +1,13 @@
-use std::{fs, ops::Range};
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    ops::Range,
+    path::{Path, PathBuf},
+};
 
 use boxed_macro::Boxed;
 use log::error;
+use serde::Serialize;
 
 pub struct Document {
     lbs: Lines,
@@ -20,67 +26,455 @@ impl Document {
         self.lbs.lines()
     }
 
+    /// the rendered status line, it is at STATUS_LINE
+    pub fn status(&self) -> String {
+        self.lbs.status_lb.output().concat()
+    }
+
+    /// the rendered key hints, it is at FOOTER_LINE
+    pub fn footer(&self) -> String {
+        self.lbs.footer_lb.output().concat()
+    }
+
+    /// while the file list is growing, the rendered rows from index `from`
+    /// and the buffer line (starts from 1) of the first one
+    pub fn appending_files(&self, from: usize) -> Option<(u64, Vec<String>)> {
+        let flb = &self.lbs.file_list_lb;
+        if !flb.appending() {
+            return None;
+        }
+        let line = self.lbs.head_lines().len() + flb.rows_offset() + from + 1;
+        Some((line as u64, flb.render_rows(from)))
+    }
+
+    /// the row (starts from 1) and content of the extract progress, None before the first frame
+    pub fn progress(&self) -> Option<(u64, String)> {
+        self.lbs
+            .head_lines()
+            .into_iter()
+            .enumerate()
+            .find(|(_, l)| l.starts_with("Progress: "))
+            .map(|(i, l)| (i as u64 + 1, l))
+    }
+
+    /// the row (starts from 1) of "Enter password: ", None if 7z did not ask for it
+    pub fn password_line(&self) -> Option<usize> {
+        self.lbs
+            .head_lines()
+            .iter()
+            .position(|l| l.starts_with("Enter password: "))
+            .map(|i| i + 1)
+    }
+
+    pub fn failure(&self) -> Option<Failure> {
+        self.lbs.inner.iter().find_map(|lb| lb.failure())
+    }
+
     #[allow(dead_code)]
     pub fn files(&self) -> Vec<String> {
         self.lbs.file_list_lb.files()
     }
 
+    /// the parsed file list as a JSON array, in archive order
+    pub fn to_json(&self) -> String {
+        self.lbs.file_list_lb.to_json()
+    }
+
+    #[allow(dead_code)]
+    pub fn summary(&self) -> Option<ListSummary> {
+        self.lbs.file_list_lb.summary()
+    }
+
+    /// the list finished without a file or folder, false until the summary is read
+    pub fn is_empty_archive(&self) -> bool {
+        self.lbs
+            .file_list_lb
+            .summary()
+            .is_some_and(|s| s.file_count() == 0 && s.folder_count() == 0)
+    }
+
+    /// the shown file names one per line,
+    /// "name\tdestination" when extracting to target, folders are skipped when flat
+    pub fn export_list(&self, target: Option<&Path>) -> String {
+        let flatten = self.lbs.extract_mode_lb.flatten;
+        self.lbs.file_list_lb.export_list(target, flatten)
+    }
+
+    /// files already in target that extracting would hit
+    pub fn conflicts(&self, target: &Path) -> Vec<PathBuf> {
+        let flatten = self.lbs.extract_mode_lb.flatten;
+        self.lbs
+            .file_list_lb
+            .extracted(target, flatten)
+            .into_iter()
+            .map(|(_, dest)| dest)
+            .collect()
+    }
+
+    /// (archive path, destination) of the files found in target, after an extract
+    pub fn extracted(&self, target: &Path) -> Vec<(String, PathBuf)> {
+        let flatten = self.lbs.extract_mode_lb.flatten;
+        self.lbs.file_list_lb.extracted(target, flatten)
+    }
+
+    /// map rendered lines back to the archive paths,
+    /// lines that are not a file row are ignored
+    pub fn select_files(&self, lines: &[String]) -> Vec<String> {
+        self.lbs.file_list_lb.select_files(lines)
+    }
+
+    /// the row (starts from 1) of the first file matching the query after the row,
+    /// ignoring case like the filter, wraps around to the top
+    pub fn find_file(&self, query: &str, after: usize) -> Option<usize> {
+        if self.lbs.technical_list_lb.is_some() {
+            return None;
+        }
+        let flb = &self.lbs.file_list_lb;
+        let first = self.lbs.head_lines().len() + flb.rows_offset() + 1;
+        let rows = flb
+            .visible()
+            .enumerate()
+            .filter(|(_, f)| matches_filter(query.trim(), &f.filename))
+            .map(|(i, _)| first + i)
+            .collect::<Vec<_>>();
+        rows.iter()
+            .find(|row| **row > after)
+            .or(rows.first())
+            .copied()
+    }
+
+    /// a compressed single tar, like .tar.gz or .tgz, needs a second extract
+    pub fn is_compressed_tar(&self) -> bool {
+        let files = self.lbs.file_list_lb.files();
+        let archive = self.lbs.filename_lb.inner.to_lowercase();
+        files.len() == 1 && files[0].to_lowercase().ends_with(".tar") && !archive.ends_with(".tar")
+    }
+
+    /// the file at the row (starts from 1) and the name it was edited to in the line,
+    /// None if the row is not a file row or the name is unchanged
+    pub fn rename_target(&self, row: usize, line: &str) -> Option<(String, String)> {
+        if self.lbs.technical_list_lb.is_some() {
+            return None;
+        }
+        let flb = &self.lbs.file_list_lb;
+        let first = self.lbs.head_lines().len() + flb.rows_offset() + 1;
+        let file = flb.visible().nth(row.checked_sub(first)?)?;
+        let new = flb.edited_name(file, line)?;
+        Some((file.filename.clone(), new))
+    }
+
+    /// the archive path of one rendered line, folders end with '/'
+    pub fn file_path(&self, line: &str) -> Option<String> {
+        self.lbs.file_list_lb.file_path(line)
+    }
+
+    pub fn sort(&mut self, key: SortKey) {
+        self.lbs.file_list_lb.sort(key);
+    }
+
+    /// hide dotfiles and hidden or system entries, the new state is returned
+    pub fn toggle_hidden(&mut self) -> bool {
+        let flb = &mut self.lbs.file_list_lb;
+        flb.hide_hidden = !flb.hide_hidden;
+        flb.hide_hidden
+    }
+
+    /// only render the files matching the pattern, an empty one clears the filter
+    pub fn filter(&mut self, pattern: &str) {
+        let pattern = pattern.trim();
+        self.lbs.file_list_lb.filter = (!pattern.is_empty()).then(|| pattern.to_string());
+    }
+
+    pub fn set_overwrite(&mut self, overwrite: OverwriteMode) {
+        self.lbs.extract_mode_lb.overwrite = overwrite;
+    }
+
+    pub fn set_human_size(&mut self, human_size: bool) {
+        self.lbs.file_list_lb.human_size = human_size;
+    }
+
+    pub fn set_max_name_width(&mut self, width: usize) {
+        self.lbs.file_list_lb.max_name_width = width;
+    }
+
+    /// the resolved key of each keymap action, like "execute" => "space+c",
+    /// the title, the footer and the password line show them
+    pub fn set_keys(&mut self, keys: HashMap<String, String>) {
+        self.lbs.title_lb.keys = keys.clone();
+        self.lbs.footer_lb.keys = keys.clone();
+        self.lbs.keys = keys;
+    }
+
+    /// the key of the action, the action name itself if it is not bound
+    pub fn key(&self, action: &str) -> String {
+        self.lbs.key(action).to_string()
+    }
+
+    /// (file name, size) of the split volumes, empty hides the section
+    pub fn set_volumes(&mut self, volumes: Vec<(String, u64)>) {
+        self.lbs.volumes_lb.volumes = volumes;
+    }
+
+    pub fn has_volumes(&self) -> bool {
+        !self.lbs.volumes_lb.volumes.is_empty()
+    }
+
+    /// the command was killed, shown under its output until the next layout
+    pub fn cancelled(&mut self, cmd: &str, partial: Vec<String>) {
+        self.lbs.inner.push(CancelledLB::new_boxed(cmd, partial));
+    }
+
     pub fn layout_list(&mut self) {
         self.lbs.new_list();
     }
 
+    pub fn layout_technical_list(&mut self) {
+        self.lbs.new_technical_list();
+    }
+
     pub fn layout_extract(&mut self) {
         self.lbs.new_extract();
     }
+
+    pub fn layout_add(&mut self) {
+        self.lbs.new_add();
+    }
+
+    pub fn layout_rename(&mut self) {
+        self.lbs.new_rename();
+    }
+
+    pub fn layout_delete(&mut self) {
+        self.lbs.new_delete();
+    }
+
+    pub fn layout_preview(&mut self) {
+        self.lbs.new_preview();
+    }
+
+    pub fn layout_test(&mut self) {
+        self.lbs.new_test();
+    }
+
+    pub fn layout_info(&mut self) {
+        self.lbs.new_info();
+    }
 }
 
 pub struct Lines {
     title_lb: TitleLB,
     filename_lb: FilenameLB,
     extract_to_lb: ExtractToLB,
+    status_lb: StatusLB,
+    footer_lb: FooterLB,
+    extract_mode_lb: ExtractModeLB,
+    // kept across layouts, it is read from the filesystem rather than 7z
+    volumes_lb: VolumesLB,
+    // only the list layout shows the summary
+    show_summary: bool,
     inner: Vec<Box<dyn LineBuilder>>,
     file_list_lb: FileListLB,
+    // "l -slt" replaces the file list when it is Some
+    technical_list_lb: Option<TechnicalListLB>,
+    // action => key, for the builders made by each layout
+    keys: HashMap<String, String>,
 }
 
-pub const PASSWORD_LINE: usize = 8;
+pub const STATUS_LINE: u64 = 5;
+pub const FOOTER_LINE: u64 = 6;
 impl Lines {
     fn new() -> Self {
         Self {
             title_lb: TitleLB::default(),
             filename_lb: FilenameLB::default(),
             extract_to_lb: ExtractToLB::default(),
+            status_lb: StatusLB::default(),
+            footer_lb: FooterLB::default(),
+            extract_mode_lb: ExtractModeLB::default(),
+            volumes_lb: VolumesLB::default(),
+            show_summary: false,
             inner: vec![],
             file_list_lb: FileListLB::default(),
+            technical_list_lb: None,
+            keys: HashMap::new(),
         }
     }
+
+    fn key<'a>(&'a self, action: &'a str) -> &'a str {
+        self.keys.get(action).map_or(action, String::as_str)
+    }
+
+    fn password_lb(&self) -> Box<dyn LineBuilder> {
+        PasswordLB::new_boxed(self.key("select_password"))
+    }
     fn new_list(&mut self) {
+        // list again after the archive changed, start a fresh file list
+        self.file_list_lb = FileListLB {
+            extract_path: std::mem::take(&mut self.file_list_lb.extract_path),
+            human_size: self.file_list_lb.human_size,
+            sort_by: self.file_list_lb.sort_by,
+            filter: self.file_list_lb.filter.take(),
+            max_name_width: self.file_list_lb.max_name_width,
+            hide_hidden: self.file_list_lb.hide_hidden,
+            encrypted: std::mem::take(&mut self.file_list_lb.encrypted),
+            ..Default::default()
+        };
+        self.show_summary = true;
+        self.technical_list_lb = None;
         let inner = vec![
             CaptureLB::new_boxed("file,"), // file size
             EmptyLB::boxed(),
-            PasswordLB::boxed(),
+            self.password_lb(),
             EmptyLB::boxed(),
+            BannerLB::boxed(),
             PropertyLB::boxed(),
+            CommentLB::boxed(),
+            EmptyLB::boxed(),
+            WarningLB::boxed(),
+            ErrorLB::boxed(),
+        ];
+        self.inner = inner;
+    }
+
+    /// the file list keeps the columnar view, blocks of "l -slt" are parsed aside
+    fn new_technical_list(&mut self) {
+        self.show_summary = false;
+        self.technical_list_lb = Some(TechnicalListLB::default());
+        let inner = vec![
+            CaptureLB::new_boxed("file,"), // file size
+            EmptyLB::boxed(),
+            self.password_lb(),
             EmptyLB::boxed(),
+            BannerLB::boxed(),
             ErrorLB::boxed(),
         ];
         self.inner = inner;
     }
 
     fn new_extract(&mut self) {
+        self.show_summary = false;
         let inner = vec![
             CaptureLB::new_boxed("file,"), // file size
             EmptyLB::boxed(),
-            PasswordLB::boxed(),
+            self.password_lb(),
             EmptyLB::boxed(),
+            BannerLB::boxed(),
             PropertyLB::boxed(),
             EmptyLB::boxed(),
+            ProgressLB::boxed(),
+            VerifyLB::boxed(),
             CaptureLB::new_boxed("Everything"), // file name
+            ChecksumLB::boxed(),
+            WarningLB::boxed(),
+            ErrorLB::boxed(),
+        ];
+        self.inner = inner;
+    }
+
+    fn new_add(&mut self) {
+        self.show_summary = false;
+        let inner = vec![
+            CaptureLB::new_boxed("archive:"), // Creating archive: | Updating archive:
+            EmptyLB::boxed(),
+            self.password_lb(),
+            EncryptionLB::boxed(),
+            EmptyLB::boxed(),
+            CaptureLB::new_boxed("Add new data"), // files and size to add
+            EmptyLB::boxed(),
+            ProgressLB::boxed(),
+            CaptureLB::new_boxed("Everything"),
+            ErrorLB::boxed(),
+        ];
+        self.inner = inner;
+    }
+
+    fn new_delete(&mut self) {
+        self.show_summary = false;
+        let inner = vec![
+            CaptureLB::new_boxed("archive:"), // Updating archive:
+            EmptyLB::boxed(),
+            self.password_lb(),
+            EmptyLB::boxed(),
+            CaptureLB::new_boxed("Delete data"), // files and size to delete
+            EmptyLB::boxed(),
+            CaptureLB::new_boxed("Everything"),
+            ErrorLB::boxed(),
+        ];
+        self.inner = inner;
+    }
+
+    fn new_rename(&mut self) {
+        self.show_summary = false;
+        let inner = vec![
+            CaptureLB::new_boxed("archive:"), // Updating archive:
+            EmptyLB::boxed(),
+            self.password_lb(),
+            EmptyLB::boxed(),
+            CaptureLB::new_boxed("Everything"),
+            ErrorLB::boxed(),
+        ];
+        self.inner = inner;
+    }
+
+    /// only the label, the file list renders the destination with "Set extract_path:"
+    fn new_preview(&mut self) {
+        self.show_summary = false;
+        let inner = vec![
+            LabelLB::new_boxed(&format!(
+                "Preview: files will be extracted as below, press `{}` to extract",
+                self.key("execute")
+            )),
+            CaptureLB::new_boxed("Existing files: "),
+        ];
+        self.inner = inner;
+    }
+
+    fn new_test(&mut self) {
+        self.show_summary = false;
+        let inner = vec![
+            CaptureLB::new_boxed("file,"), // file size
+            EmptyLB::boxed(),
+            self.password_lb(),
+            EmptyLB::boxed(),
+            PropertyLB::boxed(),
+            EmptyLB::boxed(),
+            CaptureLB::new_boxed("Everything"), // test result
+            ErrorLB::boxed(),
+        ];
+        self.inner = inner;
+    }
+
+    /// "7z i", the file list stays below the formats
+    fn new_info(&mut self) {
+        self.show_summary = false;
+        let inner = vec![
+            BannerLB::boxed(),
+            EmptyLB::boxed(),
+            InfoLB::boxed(),
             ErrorLB::boxed(),
         ];
         self.inner = inner;
     }
 
     fn input(&mut self, input: &str) {
+        // the status sees every line, the spinner moves on while running
+        if self.status_lb.input(input) {
+            return;
+        }
+        if self.footer_lb.input(input) {
+            return;
+        }
+        if self.extract_mode_lb.input(input) {
+            return;
+        }
+        if let Some(tlb) = self.technical_list_lb.as_mut() {
+            if tlb.input(input) {
+                if let Some(path) = tlb.encrypted_path(input) {
+                    self.file_list_lb.mark_encrypted(path);
+                }
+                return;
+            }
+        }
         if self.file_list_lb.input(input) {
             return;
         }
@@ -98,19 +492,42 @@ impl Lines {
     }
 
     fn lines(&self) -> Vec<String> {
+        let files = match &self.technical_list_lb {
+            Some(tlb) => tlb.output(),
+            None => self.file_list_lb.output(),
+        };
+        [self.head_lines(), files].concat()
+    }
+
+    /// everything above the file list
+    fn head_lines(&self) -> Vec<String> {
         let empty_lb = EmptyLB;
         let mut lines = [
             self.title_lb.output(),
             empty_lb.output(),
             self.filename_lb.output(),
             self.extract_to_lb.output(),
+            self.status_lb.output(),
+            self.footer_lb.output(),
+            self.extract_mode_lb.output(),
+            self.volumes_lb.output(),
             empty_lb.output(),
             self.inner.iter().flat_map(|lb| lb.output()).collect(),
             empty_lb.output(),
-            self.file_list_lb.output(),
+            // the summary is known after the whole list is parsed, render it here
+            if self.show_summary {
+                SummaryLB {
+                    summary: self.file_list_lb.summary(),
+                    filtered: self.file_list_lb.filtered(),
+                }
+                .output()
+            } else {
+                vec![]
+            },
         ]
         .concat();
-        lines.dedup();
+        // empty builders leave blank separators next to each other, keep only one
+        lines.dedup_by(|a, b| a.is_empty() && b.is_empty());
         lines
     }
 }
@@ -122,29 +539,133 @@ trait LineBuilder: Send + Sync + 'static {
         false
     }
     fn output(&self) -> Vec<String>;
+    /// the kind of failure this LineBuilder detected in the output
+    fn failure(&self) -> Option<Failure> {
+        None
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Failure {
+    // a new password may fix it
+    WrongPassword,
+    // data error, crc failed or not an archive, a password won't help
+    Corrupt,
+}
+
+impl Failure {
+    fn detect(line: &str) -> Option<Failure> {
+        let line = line.to_lowercase();
+        if line.contains("wrong password") {
+            Some(Failure::WrongPassword)
+        } else if [
+            "data error",
+            "crc failed",
+            "headers error",
+            "unexpected end",
+            "can not open the file as archive",
+            "is not archive",
+        ]
+        .iter()
+        .any(|e| line.contains(e))
+        {
+            Some(Failure::Corrupt)
+        } else {
+            None
+        }
+    }
 }
 
 trait BoxedDefault {
     fn boxed() -> Box<dyn LineBuilder>;
 }
 
-#[derive(Boxed)]
+/// "[space+c] extract" of each bound action in hints, fmt puts the key and the text together
+fn key_hints(
+    keys: &HashMap<String, String>,
+    hints: &[(&str, &str)],
+    fmt: impl Fn(&str, &str) -> String,
+) -> Vec<String> {
+    hints
+        .iter()
+        .filter_map(|(action, text)| keys.get(*action).map(|key| fmt(key, text)))
+        .collect()
+}
+
+/// the footer tells the keys of the moment, the title only the main ones
+#[derive(Default, Boxed)]
 struct TitleLB {
-    inner: String,
+    keys: HashMap<String, String>,
 }
 
-impl Default for TitleLB {
-    fn default() -> Self {
-        let title = r#"7Z-VUI, Shortcuts: `space+c`: execute extract|add; `space+q`: Quit this program; `space+r`: Retry"#;
-        Self {
-            inner: title.to_string(),
+impl LineBuilder for TitleLB {
+    fn output(&self) -> Vec<String> {
+        let hints = [
+            ("execute", "execute extract|add"),
+            ("quit", "Quit this program"),
+            ("retry", "Retry"),
+        ];
+        let shortcuts = key_hints(&self.keys, &hints, |key, text| {
+            format!("`{}`: {}", key, text)
+        });
+        if shortcuts.is_empty() {
+            vec!["7Z-VUI".to_string()]
+        } else {
+            vec![format!("7Z-VUI, Shortcuts: {}", shortcuts.join("; "))]
         }
     }
 }
 
-impl LineBuilder for TitleLB {
+/// the keys that make sense now, "Set context: list" tells what the state is
+#[derive(Default, Boxed)]
+struct FooterLB {
+    context: Option<String>,
+    keys: HashMap<String, String>,
+}
+
+impl LineBuilder for FooterLB {
+    fn input(&mut self, input: &str) -> bool {
+        match input.strip_prefix("Set context: ") {
+            Some(context) => {
+                self.context = Some(context.trim().to_string());
+                true
+            }
+            None => false,
+        }
+    }
+
     fn output(&self) -> Vec<String> {
-        vec![self.inner.clone()]
+        let hints: &[(&str, &str)] = match self.context.as_deref() {
+            Some("running") => &[
+                ("cancel", "cancel"),
+                ("quit_after_finished", "quit after finished"),
+                ("retry", "retry"),
+            ],
+            Some("password") => &[
+                ("select_password", "select saved password"),
+                ("clear_history", "clear history"),
+            ],
+            Some("list") => &[
+                ("execute", "extract"),
+                ("preview", "preview"),
+                ("extract_files", "extract selected"),
+                ("filter", "filter"),
+                ("test", "test"),
+            ],
+            Some("extracted") => &[
+                ("open_destination", "open destination"),
+                ("checksum_extracted", "checksum"),
+                ("quit", "quit"),
+            ],
+            Some("failed") => &[("retry", "retry"), ("quit", "quit")],
+            _ => &[("quit", "quit")],
+        };
+        let mut hints = key_hints(&self.keys, hints, |key, text| format!("[{}] {}", key, text));
+        if self.context.as_deref() == Some("password") {
+            hints.insert(0, "type the password then leave insert mode".to_string());
+        }
+        let hints = hints.join(" · ");
+        vec![format!("Keys: {}", hints)]
     }
 }
 
@@ -203,331 +724,1803 @@ impl LineBuilder for EmptyLB {
     }
 }
 
+/// blocks of "Key = Value" after the "----------" line of "l -slt", one block per file.
+/// each block is a fold, "Path = " starts a new one
 #[derive(Default, Boxed)]
-struct PasswordLB {
-    inner: Vec<String>,
-    password_history: Vec<String>,
-    password_history_file: Option<String>,
+struct TechnicalListLB {
+    inner: Vec<Vec<(String, String)>>,
+    capture: bool,
 }
 
-impl LineBuilder for PasswordLB {
-    fn input(&mut self, str: &str) -> bool {
-        // init password history
-        if (str.starts_with("Enter password") || str.starts_with("Input passsword"))
-            && self.inner.is_empty()
-        {
-            self.inner.push(String::new());
+impl TechnicalListLB {
+    /// the path of the block the line belongs to, if the line tells it is encrypted,
+    /// "Encrypted = +" or an AES method like "LZMA2:24 7zAES"
+    fn encrypted_path(&self, input: &str) -> Option<&str> {
+        let encrypted = match input.split_once(" = ") {
+            Some(("Encrypted", value)) => value == "+",
+            Some(("Method", value)) => value.split_whitespace().any(|m| m.contains("AES")),
+            _ => false,
+        };
+        if !encrypted {
+            return None;
         }
-        if str.starts_with("Password history file: ") {
-            // read password history from file config/password_history.txt
-            self.password_history_file = Some(
-                str.trim_start_matches("Password history file: ")
-                    .to_string(),
-            );
-            if let Ok(password_history) =
-                fs::read_to_string(self.password_history_file.as_ref().unwrap())
-            {
-                self.password_history = password_history
-                    .lines()
-                    .map(|line| line.trim().to_string())
-                    .filter(|line| !line.is_empty())
-                    .collect::<Vec<String>>();
-                if self.inner.len() > 1 {
-                    self.inner.pop();
-                }
-                self.inner.push(format!(
-                    "select password use [Ctrl+x]: {}",
-                    self.password_history
-                        .iter()
-                        .map(|p| format!("[{p}]"))
-                        .collect::<Vec<_>>()
-                        .join(", ")
-                ));
+        self.inner.last()?.first().map(|(_, path)| path.as_str())
+    }
+}
+
+impl LineBuilder for TechnicalListLB {
+    fn input(&mut self, input: &str) -> bool {
+        if !self.capture {
+            if input == "----------" {
+                self.capture = true;
+                return true;
             }
+            return false;
+        }
+        if input.is_empty() {
             return true;
         }
-        if str.starts_with("Enter password") {
-            self.inner[0] = "Enter password: ".to_string();
-            true
-        } else if str.starts_with("Input password") {
-            let password = str.trim_start_matches("Input password: ");
-            self.inner[0] = format!("Enter password: {}", password);
-            true
-        } else if str.starts_with("Save password") && self.inner.len() >= 2 {
-            if let Some(file) = &self.password_history_file {
-                self.password_history
-                    .push(str.trim_start_matches("Save password: ").to_string());
-                self.password_history.sort();
-                self.password_history.dedup();
-                fs::write(file, self.password_history.join("\n"))
-                    .expect("write password history failed");
+        match input.split_once(" = ") {
+            Some(("Path", path)) => {
+                self.inner
+                    .push(vec![("Path".to_string(), path.to_string())]);
+                true
             }
-            true
-        } else {
-            false
+            Some((key, value)) if !self.inner.is_empty() => {
+                let block = self.inner.last_mut().unwrap();
+                block.push((key.to_string(), value.to_string()));
+                true
+            }
+            _ => false,
         }
     }
     fn output(&self) -> Vec<String> {
-        self.inner.to_vec()
+        let get = |block: &[(String, String)], key: &str| {
+            block
+                .iter()
+                .find(|(k, _)| k == key)
+                .map_or(String::new(), |(_, v)| v.clone())
+        };
+        let mut lines = vec![];
+        for block in &self.inner {
+            // the fold title keeps what the columnar view hides
+            lines.push(format!(
+                "{}  Size = {}  CRC = {}  Encrypted = {} {{{{{{1",
+                get(block, "Path"),
+                get(block, "Size"),
+                get(block, "CRC"),
+                get(block, "Encrypted"),
+            ));
+            lines.extend(
+                block
+                    .iter()
+                    .skip(1)
+                    .map(|(k, v)| format!("    {} = {}", k, v)),
+            );
+        }
+        lines
     }
 }
 
-struct FileLine {
-    filename: String,
-    raw: String,
+/// the "Formats:" and "Codecs:" sections of "7z i", other sections are dropped
+#[derive(Default, Boxed)]
+struct InfoLB {
+    // the section the lines belong to, None outside of the two
+    section: Option<InfoSection>,
+    // name and extensions
+    formats: Vec<(String, String)>,
+    codecs: Vec<String>,
 }
 
-impl FileLine {
-    fn to_string(&self, extract_path: &str) -> String {
-        format!("{}{}{}", self.raw, extract_path, self.filename)
+#[derive(Clone, Copy, PartialEq)]
+enum InfoSection {
+    Formats,
+    Codecs,
+    Other,
+}
+
+/// the rendered columns fit in it
+const INFO_WIDTH: usize = 100;
+
+impl InfoLB {
+    //  0 CK...O.....XC.....    7z       7z           7z  '7z..'
+    // lib index, flags, name, extensions then the signature
+    fn parse_format(line: &str) -> Option<(String, String)> {
+        let mut tokens = line.split_whitespace().peekable();
+        tokens.next_if(|t| t.bytes().all(|b| b.is_ascii_digit()));
+        tokens.next_if(|t| {
+            t.contains('.')
+                && t.bytes()
+                    .all(|b| b == b'.' || b == b'+' || b.is_ascii_uppercase())
+        });
+        let name = tokens.next()?.to_string();
+        let is_extension = |t: &&str| {
+            let hex_byte = t.len() == 2
+                && t.bytes()
+                    .all(|b| b.is_ascii_hexdigit() && !b.is_ascii_lowercase());
+            !hex_byte
+                && t.bytes()
+                    .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b"._-~()".contains(&b))
+        };
+        let extensions = tokens
+            .take_while(is_extension)
+            .collect::<Vec<_>>()
+            .join(" ");
+        Some((name, extensions))
+    }
+
+    /// cells left to right, as many columns as fit in INFO_WIDTH
+    fn columns(cells: &[String]) -> Vec<String> {
+        let width = cells.iter().map(|c| c.chars().count()).max().unwrap_or(0) + 2;
+        let per_row = (INFO_WIDTH / width).max(1);
+        cells
+            .chunks(per_row)
+            .map(|row| {
+                row.iter()
+                    .map(|c| format!("{:<width$}", c, width = width))
+                    .collect::<String>()
+                    .trim_end()
+                    .to_string()
+            })
+            .collect()
     }
 }
 
-impl From<(&str, &[Range<usize>; 5])> for FileLine {
-    fn from((str, tem): (&str, &[Range<usize>; 5])) -> Self {
-        let chars = str.chars().collect::<Vec<char>>();
-        if chars.len() < tem[0].start || chars.len() < tem[4].start {
-            error!("parse file line failed: {}", str);
+impl LineBuilder for InfoLB {
+    fn input(&mut self, input: &str) -> bool {
+        let trimmed = input.trim();
+        if trimmed.ends_with(':') && !trimmed.contains(' ') {
+            self.section = Some(match trimmed {
+                "Formats:" => InfoSection::Formats,
+                "Codecs:" => InfoSection::Codecs,
+                _ => InfoSection::Other,
+            });
+            return true;
         }
-        let prefix = String::from_iter(&chars[tem[0].start..tem[4].start]);
-        let filename = String::from_iter(&chars[tem[4].start..]);
-        Self {
-            filename,
-            raw: prefix,
+        if trimmed.is_empty() {
+            let inside = self.section.is_some();
+            self.section = None;
+            return inside;
+        }
+        match self.section {
+            Some(InfoSection::Formats) => {
+                if let Some(format) = Self::parse_format(trimmed) {
+                    self.formats.push(format);
+                }
+                true
+            }
+            Some(InfoSection::Codecs) => {
+                // 0 4ED   303011B BCJ2, the name is the last one
+                if let Some(name) = trimmed.split_whitespace().last() {
+                    self.codecs.push(name.to_string());
+                }
+                true
+            }
+            Some(InfoSection::Other) => true,
+            None => false,
         }
     }
+    fn output(&self) -> Vec<String> {
+        let mut lines = vec![];
+        if !self.formats.is_empty() {
+            lines.push(format!("Formats ({}):", self.formats.len()));
+            let cells = self
+                .formats
+                .iter()
+                .map(|(name, ext)| match ext.is_empty() {
+                    true => name.clone(),
+                    false => format!("{} ({})", name, ext),
+                })
+                .collect::<Vec<_>>();
+            lines.extend(Self::columns(&cells));
+        }
+        if !self.codecs.is_empty() {
+            lines.push(format!("Codecs ({}):", self.codecs.len()));
+            lines.extend(Self::columns(&self.codecs));
+        }
+        lines
+    }
 }
 
+/// archive comment from the properties, "Comment = ..." then lines of it until an empty line
 #[derive(Default, Boxed)]
-struct FileListLB {
-    inner: Vec<FileLine>,
-    header_line: Option<String>,
-    begin_line: Option<String>,
-    end_line: Option<String>,
-    template: Option<[Range<usize>; 5]>,
-    summary_line: String,
+struct CommentLB {
+    inner: Vec<String>,
     capture: bool,
-    extract_path: String,
-}
-
-impl FileListLB {
-    fn files(&self) -> Vec<String> {
-        self.inner.iter().map(|f| f.filename.clone()).collect()
-    }
+    done: bool,
 }
 
-impl LineBuilder for FileListLB {
-    fn input(&mut self, str: &str) -> bool {
-        if str.starts_with("-----") {
-            if self.begin_line.is_none() {
-                self.template = Some(parse_dash_line_to_range(str));
-                self.begin_line = Some(str.to_string());
-                self.capture = true;
-            } else {
-                self.end_line = Some(str.to_string());
-            }
+impl LineBuilder for CommentLB {
+    fn input(&mut self, input: &str) -> bool {
+        if self.done {
+            false
+        } else if let Some(comment) = input.strip_prefix("Comment = ") {
+            self.inner.push(comment.to_string());
+            self.capture = true;
             true
         } else if self.capture {
-            // capture the summary line
-            if self.end_line.is_some() {
+            // the next property ends the comment too
+            let property = input.split_once(" = ").is_some_and(|(key, _)| {
+                !key.is_empty() && key.chars().all(|c| c.is_ascii_alphabetic() || c == ' ')
+            });
+            if input.is_empty() || property {
                 self.capture = false;
-                self.summary_line = str.to_string();
-            } else if str.is_empty() {
-                error!("occurs empty line in file list");
+                self.done = true;
+                false
             } else {
-                self.inner
-                    .push(FileLine::from((str, self.template.as_ref().unwrap())));
+                self.inner.push(input.to_string());
+                true
             }
-            true
-        } else if str.contains("Attr") {
-            self.header_line = Some(str.to_string());
-            true
-        } else if str.starts_with("Set extract_path:") {
-            self.extract_path = str
-                .trim_start_matches("Set extract_path:")
-                .trim()
-                .to_string();
-            true
         } else {
             false
         }
     }
-
     fn output(&self) -> Vec<String> {
-        let files = self
-            .inner
+        self.inner
             .iter()
-            .map(|f| f.to_string(&self.extract_path))
-            .collect();
-        [
-            self.header_line.clone().map_or(vec![], |l| vec![l]),
-            self.begin_line.clone().map_or(vec![], |l| vec![l]),
-            files,
-            self.end_line
-                .clone()
-                .map_or(vec![], |l| vec![l, self.summary_line.clone()]),
-        ]
-        .concat()
+            .enumerate()
+            .map(|(i, l)| {
+                if i == 0 {
+                    format!("Comment: {}", l)
+                } else {
+                    format!("         {}", l)
+                }
+            })
+            .collect()
     }
 }
 
-fn parse_dash_line_to_range(line: &str) -> [Range<usize>; 5] {
-    let mut ra: [Range<usize>; 5] = Default::default();
-    let mut cur_i = 0;
-    let mut start = 0;
-    let mut len = 0;
-    let mut last_c = ' ';
-    for (i, c) in line.char_indices() {
-        len += 1;
-        if c == ' ' {
-            if last_c == ' ' {
-                start = i + 1;
-                continue;
-            }
-            ra[cur_i].start = start;
-            ra[cur_i].end = i;
-            cur_i += 1;
-            start = i + 1;
+/// "7-Zip (z) 23.01 (x64) : Copyright (c) 1999-2023 Igor Pavlov : 2023-06-20",
+/// other lines may mention 7-Zip too, only the banner has the copyright
+#[derive(Default, Boxed)]
+struct BannerLB {
+    inner: Option<String>,
+}
+
+impl LineBuilder for BannerLB {
+    fn input(&mut self, input: &str) -> bool {
+        let banner = (input.starts_with("7-Zip") && input.contains("Copyright"))
+            || input.starts_with("p7zip Version");
+        if self.inner.is_none() && banner {
+            self.inner = Some(input.trim().to_string());
+            true
+        } else {
+            false
         }
-        last_c = c;
     }
-    ra[cur_i].start = start;
-    ra[cur_i].end = len;
-    ra
+    fn output(&self) -> Vec<String> {
+        self.inner.iter().cloned().collect()
+    }
 }
 
+/// how the files are laid out on extract, "Set flatten: on" | "Set flatten: off",
+/// "Set wrap dir: <stem>" | "Set wrap dir: off" for the auto-added subdirectory
 #[derive(Default, Boxed)]
-struct PropertyLB {
-    inner: String,
-    done: bool,
+struct ExtractModeLB {
+    flatten: bool,
+    overwrite: OverwriteMode,
+    wrap_dir: Option<String>,
 }
 
-impl LineBuilder for PropertyLB {
+impl LineBuilder for ExtractModeLB {
     fn input(&mut self, input: &str) -> bool {
-        if self.done {
-            false
-        } else if input.starts_with("Type = ") {
-            self.inner.push_str(input);
+        if let Some(flatten) = input.strip_prefix("Set flatten: ") {
+            self.flatten = flatten == "on";
             true
-        } else if input.starts_with("Method = ") {
-            self.inner.push('\t');
-            self.inner.push_str(input);
-            self.done = true;
+        } else if let Some(wrap_dir) = input.strip_prefix("Set wrap dir: ") {
+            self.wrap_dir = (wrap_dir != "off").then(|| wrap_dir.to_string());
             true
         } else {
             false
         }
     }
-
     fn output(&self) -> Vec<String> {
-        vec![self.inner.clone()]
+        let paths = if self.flatten {
+            "flat, paths in archive are dropped"
+        } else {
+            "keep paths"
+        };
+        let wrap_dir = match &self.wrap_dir {
+            Some(dir) => format!("; into {}/", dir),
+            None => String::new(),
+        };
+        vec![format!(
+            "Extract mode: {}{}; {}",
+            paths,
+            wrap_dir,
+            self.overwrite.label()
+        )]
     }
 }
 
-struct CaptureLB {
-    inner: String,
-    done: bool,
-    expression: String,
+/// what the add command encrypts,
+/// "Set encryption: headers" | "Set encryption: files" | "Set encryption: off"
+#[derive(Default, Boxed)]
+struct EncryptionLB {
+    inner: Option<String>,
 }
 
-impl CaptureLB {
-    fn new(expression: &str) -> Self {
-        Self {
-            inner: String::new(),
-            done: false,
-            expression: expression.to_string(),
+impl LineBuilder for EncryptionLB {
+    fn input(&mut self, input: &str) -> bool {
+        let Some(state) = input.strip_prefix("Set encryption: ") else {
+            return false;
+        };
+        self.inner = match state {
+            "headers" => Some("Encryption: files and headers, listing needs the password"),
+            "files" => Some("Encryption: files only, headers need the 7z format"),
+            _ => None,
         }
+        .map(String::from);
+        true
     }
-    fn new_boxed(expression: &str) -> Box<dyn LineBuilder> {
-        Box::new(Self::new(expression))
+    fn output(&self) -> Vec<String> {
+        self.inner.iter().cloned().collect()
     }
 }
 
-impl LineBuilder for CaptureLB {
+/// "Volumes: 3 files, 12.0 MiB" then one row per volume, in the number order
+#[derive(Default)]
+struct VolumesLB {
+    volumes: Vec<(String, u64)>,
+}
+
+impl LineBuilder for VolumesLB {
+    fn output(&self) -> Vec<String> {
+        if self.volumes.is_empty() {
+            return vec![];
+        }
+        let total = self.volumes.iter().map(|(_, size)| size).sum();
+        let width = self
+            .volumes
+            .iter()
+            .map(|(name, _)| name.chars().count())
+            .max()
+            .unwrap_or(0);
+        let mut lines = vec![format!(
+            "Volumes: {} files, {}",
+            self.volumes.len(),
+            human_size(total)
+        )];
+        lines.extend(
+            self.volumes
+                .iter()
+                .map(|(name, size)| format!("  {:<width$}  {}", name, human_size(*size))),
+        );
+        lines
+    }
+}
+
+/// "Checksum CRC32:" then a "path  hash" row for each "Checksum: path  hash" input
+#[derive(Default, Boxed)]
+struct ChecksumLB {
+    kind: Option<String>,
+    rows: Vec<String>,
+}
+
+impl LineBuilder for ChecksumLB {
     fn input(&mut self, input: &str) -> bool {
-        if self.done {
-            false
-        } else if input.contains(&self.expression) {
-            self.inner.push_str(input);
-            self.done = true;
-            true
+        if let Some(kind) = input.strip_prefix("Checksums: ") {
+            self.kind = Some(kind.to_string());
+            self.rows.clear();
+        } else if let Some(row) = input.strip_prefix("Checksum: ") {
+            self.rows.push(row.to_string());
         } else {
-            false
+            return false;
         }
+        true
     }
+
     fn output(&self) -> Vec<String> {
-        vec![self.inner.clone()]
+        let Some(kind) = &self.kind else {
+            return vec![];
+        };
+        let mut lines = vec![format!("Checksum {}:", kind)];
+        lines.extend(self.rows.iter().map(|row| format!("  {}", row)));
+        lines
     }
 }
 
+/// "24 files · 1 folder · 12.7 MiB" on top of the file list
 #[derive(Default, Boxed)]
-struct ErrorLB {
-    inner: String,
+struct SummaryLB {
+    summary: Option<ListSummary>,
+    // (rows shown, why) when the file list is filtered or hides hidden files
+    filtered: Option<(usize, String)>,
 }
 
-impl LineBuilder for ErrorLB {
-    fn input(&mut self, input: &str) -> bool {
-        if input.starts_with("ERROR:") {
-            self.inner.push_str(input);
+impl LineBuilder for SummaryLB {
+    fn output(&self) -> Vec<String> {
+        let Some(summary) = self.summary else {
+            return vec![];
+        };
+        if summary.file_count() == 0 && summary.folder_count() == 0 {
+            return vec!["Empty archive".to_string()];
+        }
+        let plural = |n: usize, unit: &str| {
+            if n == 1 {
+                format!("{} {}", n, unit)
+            } else {
+                format!("{} {}s", n, unit)
+            }
+        };
+        let mut parts = vec![plural(summary.file_count(), "file")];
+        if summary.folder_count() > 0 {
+            parts.push(plural(summary.folder_count(), "folder"));
+        }
+        parts.push(human_size(summary.total_size()));
+        if let Some((shown, why)) = &self.filtered {
+            parts.push(format!("{} shown{}", shown, why));
+        }
+        vec![parts.join(" · ")]
+    }
+}
+
+#[derive(Default, Boxed)]
+#[boxed(new = "new(select_key: &str)")]
+struct PasswordLB {
+    inner: Vec<String>,
+    // the key that selects the choice under the cursor
+    select_key: String,
+    password_history: Vec<String>,
+    password_history_file: Option<String>,
+    // 0 means no limit
+    password_history_limit: usize,
+    // the choices are shown as stars, they are selected by their number
+    mask: bool,
+}
+
+impl PasswordLB {
+    fn new(select_key: &str) -> Self {
+        Self {
+            select_key: select_key.to_string(),
+            ..Default::default()
+        }
+    }
+}
+
+impl LineBuilder for PasswordLB {
+    fn input(&mut self, str: &str) -> bool {
+        // init password history
+        if (str.starts_with("Enter password") || str.starts_with("Input password"))
+            && self.inner.is_empty()
+        {
+            self.inner.push(String::new());
+        }
+        if str == "Clear password history" {
+            // the typed password in inner[0] is kept
+            self.password_history.clear();
+            self.inner.truncate(1);
+            return true;
+        }
+        if str == "Mask password history" {
+            self.mask = true;
+            return true;
+        }
+        if str.starts_with("Password history limit: ") {
+            self.password_history_limit = str
+                .trim_start_matches("Password history limit: ")
+                .parse()
+                .unwrap_or(0);
+            return true;
+        }
+        if str.starts_with("Password history file: ") {
+            // read password history from file config/password_history.txt
+            self.password_history_file = Some(
+                str.trim_start_matches("Password history file: ")
+                    .to_string(),
+            );
+            if let Ok(password_history) =
+                fs::read_to_string(self.password_history_file.as_ref().unwrap())
+            {
+                self.password_history = password_history
+                    .lines()
+                    .map(|line| line.trim().to_string())
+                    .filter(|line| !line.is_empty())
+                    .collect::<Vec<String>>();
+                if self.password_history_limit > 0 {
+                    self.password_history.truncate(self.password_history_limit);
+                }
+                if self.inner.len() > 1 {
+                    self.inner.pop();
+                }
+                self.inner.push(format!(
+                    "select password use [{}]: {}",
+                    self.select_key,
+                    self.password_history
+                        .iter()
+                        .enumerate()
+                        .map(|(i, p)| if self.mask {
+                            format!("[{}: {}]", i + 1, "*".repeat(p.chars().count()))
+                        } else {
+                            format!("[{}: {p}]", i + 1)
+                        })
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ));
+            }
+            return true;
+        }
+        if str.starts_with("Enter password") {
+            self.inner[0] = "Enter password: ".to_string();
+            true
+        } else if str.starts_with("Input password") {
+            let password = str.trim_start_matches("Input password: ");
+            self.inner[0] = format!("Enter password: {}", password);
+            true
+        } else if str.starts_with("Save password") && self.inner.len() >= 2 {
+            if let Some(file) = &self.password_history_file {
+                // most recently used first
+                let password = str.trim_start_matches("Save password: ").to_string();
+                self.password_history.retain(|p| p != &password);
+                self.password_history.insert(0, password);
+                if self.password_history_limit > 0 {
+                    self.password_history.truncate(self.password_history_limit);
+                }
+                fs::write(file, self.password_history.join("\n"))
+                    .expect("write password history failed");
+            }
             true
         } else {
             false
         }
     }
     fn output(&self) -> Vec<String> {
-        vec![self.inner.clone()]
+        self.inner.to_vec()
     }
 }
 
-#[cfg(test)]
-mod test {
+#[derive(Serialize)]
+struct FileLine {
+    #[serde(rename = "name")]
+    filename: String,
+    #[serde(skip)]
+    raw: String,
+    date: String,
+    #[serde(rename = "attributes")]
+    attr: String,
+    size: u64,
+    packed: Option<u64>,
+    encrypted: bool,
+    // first file of a solid block, its packed size is the one of the whole block
+    #[serde(skip)]
+    solid_head: bool,
+}
 
-    use std::path::PathBuf;
+impl FileLine {
+    /// column is an extra rendered column placed before the filename
+    /// the path is shortened in the middle to max_width columns, 0 keeps it whole
+    fn to_string(&self, extract_path: &str, column: &str, max_width: usize) -> String {
+        let path = truncate_middle(&format!("{}{}", extract_path, self.filename), max_width);
+        match self.ratio() {
+            Some(ratio) => format!("{}{}{} ({}%)", self.raw, column, path, ratio),
+            None => format!("{}{}{}", self.raw, column, path),
+        }
+    }
 
-    use super::{parse_dash_line_to_range, FileListLB, LineBuilder};
-    #[test]
-    fn test_parse_dash_line_to_range() {
-        let ra = parse_dash_line_to_range("--- --- ---- ---- -----");
-        assert_eq!(ra, [0..3, 4..7, 8..12, 13..17, 18..23]);
-        let ra = parse_dash_line_to_range("--- --- ---- ----  -----");
-        assert_eq!(ra, [0..3, 4..7, 8..12, 13..17, 19..24]);
+    fn is_dir(&self) -> bool {
+        self.attr.starts_with('D')
     }
 
-    #[test]
-    fn test_file_list_lb() {
-        let mut flb = FileListLB::default();
-        let raw = r##"
-------------------- ----- ------------ ------------  ------------------------
-2023-12-22 16:17:58 D....            0            0  test
-2023-12-12 09:18:24 ....A       344963     13216256  test/01-e_01.png
-2023-12-12 09:18:28 ....A       821434               test/02-e_02.png
-2023-12-12 09:18:26 ....A       608418               test/03-e_03.png
-2023-12-12 09:18:28 ....A       757826               test/04-e_04.png
-2023-12-12 09:18:28 ....A       790792               test/05-e_05.png
-2023-12-12 09:18:30 ....A       712878               test/06-e_06.png
-2023-12-12 09:18:30 ....A       740854               test/07-e_07.png
-2023-12-12 09:18:30 ....A       711147               test/08-e_08.png
-2023-12-12 09:18:32 ....A       724006               test/09-e_09.png
-2023-12-12 09:18:32 ....A       637246               test/10-e_10.png
-2023-12-12 09:18:32 ....A       739784               test/11-e_11.png
-2023-12-12 09:18:34 ....A       733386               test/12-e_12.png
-2023-12-12 09:18:34 ....A       683368               test/13-e_13.png
-2023-12-12 09:18:34 ....A       740540               test/14-e_14.png
-2023-12-12 09:18:38 ....A       781016               test/15-e_15.png
-2023-12-12 09:18:38 ....A       681962               test/16-e_16.png
-2023-12-12 09:18:38 ....A       830510               test/17-e_17.png
-2023-12-12 09:18:40 ....A       220106               test/18-e_18.png
-2023-12-12 09:18:40 ....A       436832               test/19-e_19.png
-2023-12-12 09:18:40 ....A       311853               test/20-e_20.png
-2023-12-12 09:18:42 ....A       328685               test/21-MT43.jpg
-2023-12-12 09:18:42 ....A          473               test/meta.json
-------------------- ----- ------------ ------------  ------------------------
-2023-12-22 16:17:58           13338079     13216256  22 files, 1 folders
-"##;
-        raw.lines().for_each(|l| {
-            let _ = flb.input(l);
-        });
-        let lb: Box<dyn LineBuilder> = Box::new(flb);
+    /// a dotfile or in a dot folder, or the "H"idden / "S"ystem attribute of "DRHSA"
+    fn is_hidden(&self) -> bool {
+        self.attr.contains(['H', 'S'])
+            || self
+                .filename
+                .split(['/', '\\'])
+                .any(|c| c.starts_with('.') && c != "." && c != "..")
+    }
+
+    /// packed / size in percent, None for folders or files in a solid block
+    fn ratio(&self) -> Option<u64> {
+        if self.solid_head {
+            return None;
+        }
+        match self.packed {
+            Some(packed) if self.size > 0 => Some(packed * 100 / self.size),
+            _ => None,
+        }
+    }
+}
+
+/// the text of a column, empty if the line is shorter than the column
+fn column(chars: &[char], range: &Range<usize>) -> String {
+    let start = range.start.min(chars.len());
+    let end = range.end.min(chars.len());
+    String::from_iter(&chars[start..end]).trim().to_string()
+}
+
+/// column ranges of the dash line, the name is always the last one,
+/// the others are date, attr, size and packed in order, a format may have fewer
+type Template = Vec<Range<usize>>;
+
+/// a column before the name, None if the listing does not have it
+fn template_range(tem: &Template, i: usize) -> Option<&Range<usize>> {
+    (i + 1 < tem.len()).then(|| &tem[i])
+}
+
+/// where the name column starts, 0 without a template
+fn name_start(tem: &Template) -> usize {
+    tem.last().map_or(0, |r| r.start)
+}
+
+impl From<(&str, &Template)> for FileLine {
+    fn from((str, tem): (&str, &Template)) -> Self {
+        let chars = str.chars().collect::<Vec<char>>();
+        let at = name_start(tem);
+        let (prefix, filename) = if chars.len() >= at {
+            let from = tem.first().map_or(0, |r| r.start).min(at);
+            (
+                String::from_iter(&chars[from..at]),
+                String::from_iter(&chars[at..]),
+            )
+        } else {
+            // the line ends before the name column, the text after the last gap is the name
+            error!("parse file line failed, line is short: {}", str);
+            let line = str.trim();
+            match line.rsplit_once("  ").or_else(|| line.rsplit_once(' ')) {
+                Some((prefix, name)) => (format!("{}  ", prefix), name.trim().to_string()),
+                None => (String::new(), line.to_string()),
+            }
+        };
+        let get = |i: usize| template_range(tem, i).map_or(String::new(), |r| column(&chars, r));
+        let date = get(0);
+        let attr = get(1);
+        let size = get(2).parse::<u64>().unwrap_or(0);
+        let packed = get(3).parse::<u64>().ok();
+        Self {
+            filename,
+            raw: prefix,
+            date,
+            attr,
+            size,
+            packed,
+            encrypted: false,
+            solid_head: false,
+        }
+    }
+}
+
+/// the row after the last dash line, "13338079  13216256  22 files, 1 folders"
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ListSummary {
+    total_size: u64,
+    // some formats do not report the packed size
+    total_packed: Option<u64>,
+    file_count: usize,
+    folder_count: usize,
+}
+
+#[allow(dead_code)]
+impl ListSummary {
+    pub fn total_size(&self) -> u64 {
+        self.total_size
+    }
+
+    pub fn total_packed(&self) -> Option<u64> {
+        self.total_packed
+    }
+
+    pub fn file_count(&self) -> usize {
+        self.file_count
+    }
+
+    pub fn folder_count(&self) -> usize {
+        self.folder_count
+    }
+}
+
+impl From<(&str, &Template)> for ListSummary {
+    fn from((str, tem): (&str, &Template)) -> Self {
+        let chars = str.chars().collect::<Vec<char>>();
+        let get = |i: usize| template_range(tem, i).map_or(String::new(), |r| column(&chars, r));
+        let mut summary = Self {
+            total_size: get(2).parse::<u64>().unwrap_or(0),
+            total_packed: get(3).parse::<u64>().ok(),
+            ..Default::default()
+        };
+        let counts = String::from_iter(&chars[name_start(tem).min(chars.len())..]);
+        // "22 files, 1 folders" | "1 files" | "3 folders"
+        for count in counts.split(',') {
+            let mut words = count.split_whitespace();
+            let (Some(num), Some(unit)) = (words.next(), words.next()) else {
+                continue;
+            };
+            let Ok(num) = num.parse::<usize>() else {
+                continue;
+            };
+            if unit.starts_with("file") {
+                summary.file_count = num;
+            } else if unit.starts_with("folder") {
+                summary.folder_count = num;
+            }
+        }
+        summary
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    Name,
+    Size,
+    Date,
+}
+
+impl SortKey {
+    /// the next key when cycling, starting from Name
+    pub fn cycle(current: Option<SortKey>) -> SortKey {
+        match current {
+            None | Some(SortKey::Date) => SortKey::Name,
+            Some(SortKey::Name) => SortKey::Size,
+            Some(SortKey::Size) => SortKey::Date,
+        }
+    }
+}
+
+/// what 7z does with a file that already exists on extract
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum OverwriteMode {
+    #[default]
+    Overwrite,
+    Skip,
+    Rename,
+}
+
+impl OverwriteMode {
+    pub fn cycle(self) -> OverwriteMode {
+        match self {
+            OverwriteMode::Overwrite => OverwriteMode::Skip,
+            OverwriteMode::Skip => OverwriteMode::Rename,
+            OverwriteMode::Rename => OverwriteMode::Overwrite,
+        }
+    }
+
+    /// -aoa: overwrite all, -aos: skip existing, -aou: rename extracted
+    pub fn switch(self) -> &'static str {
+        match self {
+            OverwriteMode::Overwrite => "-aoa",
+            OverwriteMode::Skip => "-aos",
+            OverwriteMode::Rename => "-aou",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            OverwriteMode::Overwrite => "overwrite existing",
+            OverwriteMode::Skip => "skip existing",
+            OverwriteMode::Rename => "rename extracted",
+        }
+    }
+}
+
+// prefix of the rows that need the password
+const ENCRYPTED_MARKER: &str = "* ";
+
+#[derive(Default, Boxed)]
+struct FileListLB {
+    // (index in archive order, file line)
+    inner: Vec<(usize, FileLine)>,
+    header_line: Option<String>,
+    begin_line: Option<String>,
+    end_line: Option<String>,
+    template: Option<Template>,
+    summary_line: String,
+    summary: Option<ListSummary>,
+    capture: bool,
+    extract_path: String,
+    human_size: bool,
+    sort_by: Option<SortKey>,
+    // substring, or glob if it has '*' or '?'
+    filter: Option<String>,
+    // rendered names are shortened to it, 0 means no limit
+    max_name_width: usize,
+    // hidden rows are still in the archive, extract takes them as before
+    hide_hidden: bool,
+    // the columnar list does not tell it, "l -slt" does, kept when listing again
+    encrypted: HashSet<String>,
+}
+
+impl FileListLB {
+    fn mark_encrypted(&mut self, path: &str) {
+        for (_, f) in self.inner.iter_mut().filter(|(_, f)| f.filename == path) {
+            f.encrypted = true;
+        }
+        self.encrypted.insert(path.to_string());
+    }
+
+    /// stable sort, equal keys keep the archive order
+    fn sort(&mut self, key: SortKey) {
+        self.sort_by = Some(key);
+        self.inner.sort_by(|(ai, a), (bi, b)| {
+            match key {
+                SortKey::Name => a.filename.cmp(&b.filename),
+                SortKey::Size => a.size.cmp(&b.size),
+                SortKey::Date => a.date.cmp(&b.date),
+            }
+            .then(ai.cmp(bi))
+        });
+    }
+
+    /// width of the size column, the human-readable column uses the same width
+    fn size_width(&self) -> usize {
+        self.template
+            .as_ref()
+            .and_then(|tem| template_range(tem, 2))
+            .map_or(0, |r| r.len())
+    }
+
+    /// insert a column right before the filename column of a non-file line
+    fn insert_column(&self, line: &str, column: &str) -> String {
+        match &self.template {
+            Some(tem) if self.human_size => {
+                let chars = line.chars().collect::<Vec<char>>();
+                let at = name_start(tem).min(chars.len());
+                format!(
+                    "{}{:>width$}  {}",
+                    String::from_iter(&chars[..at]),
+                    column,
+                    String::from_iter(&chars[at..]),
+                    width = self.size_width()
+                )
+            }
+            _ => line.to_string(),
+        }
+    }
+
+    fn render_file(&self, f: &FileLine) -> String {
+        let line = self.render_columns(f);
+        if f.encrypted {
+            format!("{}{}", ENCRYPTED_MARKER, line)
+        } else {
+            line
+        }
+    }
+
+    fn render_columns(&self, f: &FileLine) -> String {
+        if self.human_size {
+            let size = if f.is_dir() {
+                String::new()
+            } else {
+                human_size(f.size)
+            };
+            let column = format!("{:>width$}  ", size, width = self.size_width());
+            f.to_string(&self.extract_path, &column, self.max_name_width)
+        } else {
+            f.to_string(&self.extract_path, "", self.max_name_width)
+        }
+    }
+
+    /// rows passing the filter, in the displayed order
+    fn visible(&self) -> impl Iterator<Item = &FileLine> {
+        self.inner
+            .iter()
+            .map(|(_, f)| f)
+            .filter(|f| match &self.filter {
+                Some(pattern) => matches_filter(pattern, &f.filename),
+                None => true,
+            })
+            .filter(|f| !(self.hide_hidden && f.is_hidden()))
+    }
+
+    /// (rows shown, why the others are not), None if every row is shown,
+    /// why follows "N shown": ` for "*.txt", hidden skipped` or `, hidden skipped`
+    fn filtered(&self) -> Option<(usize, String)> {
+        let mut why = String::new();
+        if let Some(pattern) = &self.filter {
+            why.push_str(&format!(" for \"{}\"", pattern));
+        }
+        if self.hide_hidden {
+            why.push_str(", hidden skipped");
+        }
+        (!why.is_empty()).then(|| (self.visible().count(), why))
+    }
+
+    fn files(&self) -> Vec<String> {
+        self.inner.iter().map(|(_, f)| f.filename.clone()).collect()
+    }
+
+    /// names were decoded lossily from 7z output, so they are valid UTF-8 here
+    fn to_json(&self) -> String {
+        let mut files = self.inner.iter().collect::<Vec<_>>();
+        files.sort_by_key(|(i, _)| *i);
+        let files = files.into_iter().map(|(_, f)| f).collect::<Vec<_>>();
+        serde_json::to_string_pretty(&files).unwrap_or_else(|e| {
+            error!("serialize file list failed: {}", e);
+            "[]".to_string()
+        })
+    }
+
+    /// None until the summary row is captured
+    fn summary(&self) -> Option<ListSummary> {
+        self.summary
+    }
+
+    /// folders are created anyway, only files can be overwritten
+    fn export_list(&self, target: Option<&Path>, flatten: bool) -> String {
+        self.visible()
+            .filter_map(|f| {
+                let Some(target) = target else {
+                    return Some(format!("{}\n", f.filename));
+                };
+                let dest = if flatten {
+                    if f.is_dir() {
+                        return None;
+                    }
+                    target.join(Path::new(&f.filename).file_name()?)
+                } else {
+                    target.join(&f.filename)
+                };
+                Some(format!("{}\t{}\n", f.filename, dest.display()))
+            })
+            .collect()
+    }
+
+    fn extracted(&self, target: &Path, flatten: bool) -> Vec<(String, PathBuf)> {
+        if !target.is_dir() {
+            return vec![];
+        }
+        self.inner
+            .iter()
+            .filter(|(_, f)| !f.is_dir())
+            .filter_map(|(_, f)| {
+                let dest = if flatten {
+                    target.join(Path::new(&f.filename).file_name()?)
+                } else {
+                    target.join(&f.filename)
+                };
+                dest.exists().then(|| (f.filename.clone(), dest))
+            })
+            .collect()
+    }
+
+    /// rows are only appended between the two dash lines
+    fn appending(&self) -> bool {
+        self.capture && self.end_line.is_none()
+    }
+
+    /// output lines above the first row
+    fn rows_offset(&self) -> usize {
+        self.header_line.iter().count() + self.begin_line.iter().count()
+    }
+
+    /// the name in an edited row, None if it can not be told from the rendered one:
+    /// unchanged, the columns before it changed, a preview or a shortened name
+    fn edited_name(&self, f: &FileLine, line: &str) -> Option<String> {
+        let rendered = self.render_file(f);
+        if rendered == line
+            || !self.extract_path.is_empty()
+            || truncate_middle(&f.filename, self.max_name_width) != f.filename
+        {
+            return None;
+        }
+        let ratio = f.ratio().map_or(String::new(), |r| format!(" ({}%)", r));
+        let prefix = rendered.strip_suffix(&format!("{}{}", f.filename, ratio))?;
+        let name = line.strip_prefix(prefix)?;
+        let name = name.strip_suffix(&ratio).unwrap_or(name).trim();
+        (name != f.filename).then(|| name.to_string())
+    }
+
+    fn render_rows(&self, from: usize) -> Vec<String> {
+        self.visible()
+            .skip(from)
+            .map(|f| self.render_file(f))
+            .collect()
+    }
+
+    fn file_path(&self, line: &str) -> Option<String> {
+        self.inner
+            .iter()
+            .find(|(_, f)| self.render_file(f) == line)
+            .map(|(_, f)| {
+                if f.is_dir() {
+                    format!("{}/", f.filename)
+                } else {
+                    f.filename.clone()
+                }
+            })
+    }
+
+    fn select_files(&self, lines: &[String]) -> Vec<String> {
+        lines
+            .iter()
+            .filter_map(|line| {
+                self.inner
+                    .iter()
+                    .find(|(_, f)| self.render_file(f) == *line)
+                    .map(|(_, f)| f.filename.clone())
+            })
+            .collect()
+    }
+}
+
+impl LineBuilder for FileListLB {
+    fn input(&mut self, str: &str) -> bool {
+        if str.starts_with("Set extract_path:") {
+            self.extract_path = str
+                .trim_start_matches("Set extract_path:")
+                .trim()
+                .to_string();
+            true
+        } else if str.starts_with("-----") {
+            if self.begin_line.is_none() {
+                self.template = Some(parse_dash_line_to_range(str));
+                self.begin_line = Some(str.to_string());
+                self.capture = true;
+            } else {
+                self.end_line = Some(str.to_string());
+                // the files after the head of a solid block have no packed size
+                for i in 1..self.inner.len() {
+                    let next = &self.inner[i].1;
+                    if next.packed.is_none() && !next.is_dir() {
+                        let head = &mut self.inner[i - 1].1;
+                        head.solid_head = head.packed.is_some();
+                    }
+                }
+                if let Some(key) = self.sort_by {
+                    self.sort(key);
+                }
+            }
+            true
+        } else if self.capture {
+            // capture the summary line
+            if self.end_line.is_some() {
+                self.capture = false;
+                self.summary_line = str.to_string();
+                self.summary = self
+                    .template
+                    .as_ref()
+                    .map(|tem| ListSummary::from((str, tem)));
+            } else if str.is_empty() {
+                error!("occurs empty line in file list");
+            } else {
+                let mut line = FileLine::from((str, self.template.as_ref().unwrap()));
+                line.encrypted = self.encrypted.contains(&line.filename);
+                self.inner.push((self.inner.len(), line));
+            }
+            true
+        } else if str.contains("Attr") {
+            self.header_line = Some(str.to_string());
+            true
+        } else {
+            false
+        }
+    }
+
+    fn output(&self) -> Vec<String> {
+        let files = self.visible().map(|f| self.render_file(f)).collect();
+        let dash = "-".repeat(self.size_width());
+        let summary_size = self
+            .template
+            .as_ref()
+            .and_then(|tem| template_range(tem, 2))
+            .map_or(String::new(), |r| {
+                let chars = self.summary_line.chars().collect::<Vec<char>>();
+                column(&chars, r)
+                    .parse::<u64>()
+                    .map_or(String::new(), human_size)
+            });
+        [
+            self.header_line
+                .as_ref()
+                .map_or(vec![], |l| vec![self.insert_column(l, "Human")]),
+            self.begin_line
+                .as_ref()
+                .map_or(vec![], |l| vec![self.insert_column(l, &dash)]),
+            files,
+            self.end_line.as_ref().map_or(vec![], |l| {
+                vec![
+                    self.insert_column(l, &dash),
+                    self.insert_column(&self.summary_line, &summary_size),
+                ]
+            }),
+        ]
+        .concat()
+    }
+}
+
+/// glob when the pattern has '*' or '?', otherwise a substring, both ignore case
+fn matches_filter(pattern: &str, filename: &str) -> bool {
+    let pattern = pattern.to_lowercase();
+    let filename = filename.to_lowercase();
+    if !pattern.contains(['*', '?']) {
+        return filename.contains(&pattern);
+    }
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let filename = filename.chars().collect::<Vec<_>>();
+    // backtrack to the last '*', let it eat one more char
+    let (mut p, mut f) = (0, 0);
+    let mut star = None;
+    while f < filename.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, f));
+                p += 1;
+            }
+            Some('?') => {
+                p += 1;
+                f += 1;
+            }
+            Some(c) if *c == filename[f] => {
+                p += 1;
+                f += 1;
+            }
+            _ => match star {
+                Some((sp, sf)) => {
+                    star = Some((sp, sf + 1));
+                    p = sp + 1;
+                    f = sf + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// columns the char takes in the terminal, CJK and most emoji take two
+fn char_width(c: char) -> usize {
+    match c as u32 {
+        0x1100..=0x115F
+        | 0x2E80..=0x303E
+        | 0x3041..=0x33FF
+        | 0x3400..=0x4DBF
+        | 0x4E00..=0x9FFF
+        | 0xA000..=0xA4CF
+        | 0xAC00..=0xD7A3
+        | 0xF900..=0xFAFF
+        | 0xFE30..=0xFE4F
+        | 0xFF00..=0xFF60
+        | 0xFFE0..=0xFFE6
+        | 0x1F300..=0x1F64F
+        | 0x1F900..=0x1F9FF
+        | 0x20000..=0x3FFFD => 2,
+        _ => 1,
+    }
+}
+
+/// "a/b/c/d/e/z.png" to "a/b/...e/z.png" within max_width columns, whole chars only,
+/// the tail gets the larger half, it has the file name
+fn truncate_middle(path: &str, max_width: usize) -> String {
+    const ELLIPSIS: &str = "...";
+    let width = path.chars().map(char_width).sum::<usize>();
+    if max_width == 0 || width <= max_width {
+        return path.to_string();
+    }
+    let budget = max_width.saturating_sub(ELLIPSIS.len());
+    let take = |chars: &mut dyn Iterator<Item = char>, budget: usize| {
+        let mut taken = vec![];
+        let mut used = 0;
+        for c in chars {
+            used += char_width(c);
+            if used > budget {
+                break;
+            }
+            taken.push(c);
+        }
+        taken
+    };
+    let tail = take(&mut path.chars().rev(), budget - budget / 2);
+    let tail_width = tail.iter().copied().map(char_width).sum::<usize>();
+    let head = take(&mut path.chars(), budget - tail_width);
+    format!(
+        "{}{}{}",
+        String::from_iter(head),
+        ELLIPSIS,
+        String::from_iter(tail.into_iter().rev())
+    )
+}
+
+/// bytes to B/KiB/MiB/GiB with one decimal place
+fn human_size(size: u64) -> String {
+    const UNITS: [&str; 3] = ["KiB", "MiB", "GiB"];
+    if size < 1024 {
+        return format!("{} B", size);
+    }
+    let mut value = size as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+/// one range per run of dashes, as many as the listing has
+fn parse_dash_line_to_range(line: &str) -> Template {
+    let mut ra = vec![];
+    let mut start = None;
+    let mut len = 0;
+    for (i, c) in line.chars().enumerate() {
+        len += 1;
+        match (c == ' ', start) {
+            (false, None) => start = Some(i),
+            (true, Some(s)) => {
+                ra.push(s..i);
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        ra.push(s..len);
+    }
+    ra
+}
+
+#[derive(Default, Boxed)]
+struct PropertyLB {
+    inner: String,
+    done: bool,
+}
+
+impl LineBuilder for PropertyLB {
+    fn input(&mut self, input: &str) -> bool {
+        if self.done {
+            false
+        } else if input.starts_with("Type = ") {
+            // split volumes report "Type = Split" before the real type
+            if !self.inner.is_empty() {
+                self.inner.push('\t');
+            }
+            self.inner.push_str(input);
+            true
+        } else if input.starts_with("Volumes = ") {
+            self.inner.push('\t');
+            self.inner.push_str(input);
+            true
+        } else if input.starts_with("Method = ") {
+            self.inner.push('\t');
+            self.inner.push_str(input);
+            self.done = true;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn output(&self) -> Vec<String> {
+        vec![self.inner.clone()]
+    }
+}
+
+/// "Cancelled: extract, 2 files written, may be partial" then one row per file
+#[derive(Boxed)]
+#[boxed(new = "new(cmd: &str, partial: Vec<String>)")]
+struct CancelledLB {
+    cmd: String,
+    partial: Vec<String>,
+}
+
+impl CancelledLB {
+    fn new(cmd: &str, partial: Vec<String>) -> Self {
+        Self {
+            cmd: cmd.to_string(),
+            partial,
+        }
+    }
+}
+
+impl LineBuilder for CancelledLB {
+    fn output(&self) -> Vec<String> {
+        let mut lines = vec![match self.partial.len() {
+            0 => format!("Cancelled: {}", self.cmd),
+            1 => format!("Cancelled: {}, 1 file written, may be partial", self.cmd),
+            n => format!(
+                "Cancelled: {}, {} files written, may be partial",
+                self.cmd, n
+            ),
+        }];
+        lines.extend(self.partial.iter().map(|f| format!("  {}", f)));
+        lines
+    }
+}
+
+#[derive(Boxed)]
+#[boxed(new = "new(expression: &str)")]
+struct CaptureLB {
+    inner: String,
+    done: bool,
+    expression: String,
+}
+
+impl CaptureLB {
+    fn new(expression: &str) -> Self {
+        Self {
+            inner: String::new(),
+            done: false,
+            expression: expression.to_string(),
+        }
+    }
+}
+
+impl LineBuilder for CaptureLB {
+    fn input(&mut self, input: &str) -> bool {
+        if self.done {
+            false
+        } else if input.contains(&self.expression) {
+            self.inner.push_str(input);
+            self.done = true;
+            true
+        } else {
+            false
+        }
+    }
+    fn output(&self) -> Vec<String> {
+        vec![self.inner.clone()]
+    }
+}
+
+/// a fixed line
+#[derive(Boxed)]
+#[boxed(new = "new(label: &str)")]
+struct LabelLB {
+    inner: String,
+}
+
+impl LabelLB {
+    fn new(label: &str) -> Self {
+        Self {
+            inner: label.to_string(),
+        }
+    }
+}
+
+impl LineBuilder for LabelLB {
+    fn output(&self) -> Vec<String> {
+        vec![self.inner.clone()]
+    }
+}
+
+/// keep the latest percentage of the progress frames, like "  45% 10 - file.png"
+#[derive(Default, Boxed)]
+struct ProgressLB {
+    percent: Option<u8>,
+}
+
+impl LineBuilder for ProgressLB {
+    fn input(&mut self, input: &str) -> bool {
+        let input = input.trim_start();
+        match input.split_once('%') {
+            Some((num, _)) if !num.is_empty() && num.bytes().all(|b| b.is_ascii_digit()) => {
+                match num.parse::<u8>() {
+                    Ok(percent) if percent <= 100 => {
+                        self.percent = Some(percent);
+                        true
+                    }
+                    _ => false,
+                }
+            }
+            _ => false,
+        }
+    }
+
+    fn output(&self) -> Vec<String> {
+        vec![self
+            .percent
+            .map_or(String::new(), |p| format!("Progress: {}%", p))]
+    }
+}
+
+/// "Verify: 2 files OK, 0 errors" after extracting, it only looks at the lines
+/// and lets them go on to the Everything capture and ErrorLB
+#[derive(Default, Boxed)]
+struct VerifyLB {
+    errors: usize,
+    // "Files: N", 7z does not print it for a single file
+    files: Option<usize>,
+    done: bool,
+}
+
+impl LineBuilder for VerifyLB {
+    fn input(&mut self, input: &str) -> bool {
+        if input.contains("Data Error") || input.contains("CRC Failed") {
+            self.errors += 1;
+        } else if input.starts_with("Everything is Ok") || input.starts_with("Sub items Errors:") {
+            self.done = true;
+        } else if let Some(files) = input.strip_prefix("Files: ") {
+            self.files = files.trim().parse().ok();
+        }
+        false
+    }
+
+    fn output(&self) -> Vec<String> {
+        if !self.done {
+            return vec![];
+        }
+        let line = match self.files {
+            Some(files) => format!(
+                "Verify: {} files OK, {} errors",
+                files.saturating_sub(self.errors),
+                self.errors
+            ),
+            None if self.errors == 0 => "Verify: all files OK".to_string(),
+            None => format!("Verify: {} errors", self.errors),
+        };
+        vec![line]
+    }
+}
+
+const SPINNER: [char; 4] = ['|', '/', '-', '\\'];
+// output lines before the spinner moves to the next frame
+const SPINNER_STEP: usize = 32;
+
+/// "Status: running list" | "Status: idle" | "Status: list failed ..."
+#[derive(Default, Boxed)]
+struct StatusLB {
+    status: Option<String>,
+    running: bool,
+    ticks: usize,
+}
+
+impl LineBuilder for StatusLB {
+    fn input(&mut self, input: &str) -> bool {
+        if let Some(status) = input.strip_prefix("Status: ") {
+            self.running = status.starts_with("running");
+            self.status = Some(status.to_string());
+            self.ticks = 0;
+            true
+        } else {
+            if self.running {
+                self.ticks += 1;
+            }
+            false
+        }
+    }
+    fn output(&self) -> Vec<String> {
+        let status = self.status.as_deref().unwrap_or("idle");
+        if self.running {
+            let frame = SPINNER[self.ticks / SPINNER_STEP % SPINNER.len()];
+            vec![format!("Status: {} {}", status, frame)]
+        } else {
+            vec![format!("Status: {}", status)]
+        }
+    }
+}
+
+/// "WARNING: ..." lines, every one is kept, the command went on after them
+#[derive(Default, Boxed)]
+struct WarningLB {
+    inner: Vec<String>,
+}
+
+impl LineBuilder for WarningLB {
+    fn input(&mut self, input: &str) -> bool {
+        // "WARNING: Cannot open file" | "WARNINGS for files:"
+        if input.starts_with("WARNING") {
+            self.inner.push(input.to_string());
+            true
+        } else {
+            false
+        }
+    }
+    fn output(&self) -> Vec<String> {
+        self.inner.clone()
+    }
+}
+
+#[derive(Default, Boxed)]
+struct ErrorLB {
+    // one row per "ERROR:" line
+    inner: Vec<String>,
+    failure: Option<Failure>,
+    // (wrong passwords in a row, max before giving up the hint)
+    attempts: Option<(usize, usize)>,
+}
+
+impl LineBuilder for ErrorLB {
+    fn input(&mut self, input: &str) -> bool {
+        if let Some(attempts) = input.strip_prefix("Password attempts: ") {
+            self.attempts = attempts
+                .split_once('/')
+                .and_then(|(n, max)| Some((n.parse().ok()?, max.parse().ok()?)));
+            true
+        } else if input.starts_with("ERROR:") {
+            self.inner.push(input.to_string());
+            // wrong password wins, it is the one the user can fix
+            match Failure::detect(input) {
+                Some(Failure::WrongPassword) => self.failure = Some(Failure::WrongPassword),
+                Some(failure) if self.failure.is_none() => self.failure = Some(failure),
+                _ => {}
+            }
+            true
+        } else {
+            false
+        }
+    }
+    fn output(&self) -> Vec<String> {
+        let mut lines = self.inner.clone();
+        match self.failure {
+            Some(Failure::WrongPassword) => match self.attempts {
+                Some((n, max)) if max > 0 && n >= max => lines.push(format!(
+                    "Wrong password {} times, the password may be wrong or the archive corrupt",
+                    n
+                )),
+                Some((n, _)) => lines.push(format!(
+                    "Wrong password (attempt {}), edit the password line then try again",
+                    n
+                )),
+                None => {
+                    lines.push("Wrong password, edit the password line then try again".to_string())
+                }
+            },
+            Some(Failure::Corrupt) => {
+                lines.push("Archive is corrupt, it can not be recovered by a password".to_string())
+            }
+            None => {}
+        }
+        lines
+    }
+    fn failure(&self) -> Option<Failure> {
+        self.failure
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::{
+        collections::HashMap,
+        path::{Path, PathBuf},
+    };
+
+    use crate::nvim::{key_labels, keymaps};
+
+    use super::{
+        human_size, matches_filter, parse_dash_line_to_range, truncate_middle, BannerLB,
+        ChecksumLB, CommentLB, Document, EncryptionLB, ErrorLB, ExtractModeLB, Failure, FileLine,
+        FileListLB, InfoLB, LineBuilder, ListSummary, PasswordLB, ProgressLB, SortKey, StatusLB,
+        SummaryLB, TechnicalListLB, VerifyLB, WarningLB, ENCRYPTED_MARKER, FOOTER_LINE, INFO_WIDTH,
+        SPINNER_STEP, STATUS_LINE,
+    };
+
+    /// feed canned 7z output through a document with the layout, line by line
+    fn feed(layout: fn(&mut Document), output: &str) -> Vec<String> {
+        let mut doc = Document::new();
+        layout(&mut doc);
+        doc.input("Extract file: /tmp/test.7z");
+        doc.input("Extract to: /tmp/test");
+        output.lines().for_each(|l| doc.input(l));
+        doc.output()
+    }
+
+    const LIST_OUTPUT: &str = r##"
+7-Zip (z) 23.01 (x64) : Copyright (c) 1999-2023 Igor Pavlov : 2023-06-20
+ 64-bit locale=en_US.UTF-8 Threads:16 OPEN_MAX:1024
+
+Scanning the drive for archives:
+1 file, 13338079 bytes (13 MiB)
+
+Listing archive: /tmp/test.7z
+
+--
+Path = /tmp/test.7z
+Type = 7z
+Physical Size = 13338079
+Headers Size = 298
+Method = LZMA2:24
+Solid = +
+Blocks = 1
+
+   Date      Time    Attr         Size   Compressed  Name
+------------------- ----- ------------ ------------  ------------------------
+2023-12-22 16:17:58 D....            0            0  test
+2023-12-12 09:18:24 ....A       344963     13216256  test/01-e_01.png
+2023-12-12 09:18:28 ....A       821434               test/02-e_02.png
+------------------- ----- ------------ ------------  ------------------------
+2023-12-22 16:17:58            1166397     13216256  2 files, 1 folders
+"##;
+
+    #[test]
+    fn test_feed_list() {
+        let lines = feed(Document::layout_list, LIST_OUTPUT);
+        let at = |prefix: &str| lines.iter().position(|l| l.starts_with(prefix));
+        assert_eq!(lines[2], "Extract file: /tmp/test.7z");
+        assert_eq!(lines[3], "Extract to: /tmp/test");
+        assert_eq!(
+            lines[at("1 file,").unwrap()],
+            "1 file, 13338079 bytes (13 MiB)"
+        );
+        assert!(at("7-Zip (z) 23.01").is_some());
+        assert_eq!(
+            lines[at("Type = ").unwrap()],
+            "Type = 7z\tMethod = LZMA2:24"
+        );
+        let summary = at("2 files · 1 folder").unwrap();
+        assert!(lines[summary + 1].contains("Attr"));
+        assert!(lines[summary + 3].ends_with("  test"));
+        assert_eq!(lines.last().unwrap(), &LIST_OUTPUT.lines().last().unwrap());
+        // parsed lines are not repeated as raw output
+        assert!(at("Listing archive:").is_none());
+        assert!(at("Path = ").is_none());
+    }
+
+    #[test]
+    fn test_feed_extract() {
+        let output = r##"
+7-Zip (z) 23.01 (x64) : Copyright (c) 1999-2023 Igor Pavlov : 2023-06-20
+
+Scanning the drive for archives:
+1 file, 13338079 bytes (13 MiB)
+
+Extracting archive: /tmp/test.7z
+--
+Path = /tmp/test.7z
+Type = 7z
+Method = LZMA2:24
+
+  0%
+ 45% 1 - test/01-e_01.png
+Everything is Ok
+
+Folders: 1
+Files: 2
+Size:       1166397
+Compressed: 13338079
+"##;
+        let lines = feed(Document::layout_extract, output);
+        assert!(lines.contains(&"Progress: 45%".to_string()));
+        assert!(lines.contains(&"Everything is Ok".to_string()));
+        assert!(lines.contains(&"Verify: 2 files OK, 0 errors".to_string()));
+        assert!(!lines.iter().any(|l| l.starts_with("ERROR:")));
+    }
+
+    #[test]
+    fn test_verify_lb() {
+        let output = r##"
+Extracting archive: /tmp/test.7z
+ERROR: Data Error : test/01-e_01.png
+ERROR: CRC Failed : test/02-e_02.png
+
+Sub items Errors: 2
+
+Archives with Errors: 1
+"##;
+        let lines = feed(Document::layout_extract, output);
+        assert!(lines.contains(&"Verify: 2 errors".to_string()));
+        // still reported as errors
+        assert!(lines.iter().any(|l| l.contains("01-e_01.png")));
+
+        let mut vlb = VerifyLB::default();
+        assert!(vlb.output().is_empty());
+        assert!(!vlb.input("Everything is Ok"));
+        assert_eq!(vlb.output(), vec!["Verify: all files OK"]);
+    }
+
+    #[test]
+    fn test_feed_wrong_password() {
+        let output = r##"
+7-Zip (z) 23.01 (x64) : Copyright (c) 1999-2023 Igor Pavlov : 2023-06-20
+
+Scanning the drive for archives:
+1 file, 13338079 bytes (13 MiB)
+
+Extracting archive: /tmp/test.7z
+Enter password (will not be echoed):
+ERROR: Data Error in encrypted file. Wrong password? : test/01-e_01.png
+"##;
+        let mut doc = Document::new();
+        doc.layout_extract();
+        output.lines().for_each(|l| doc.input(l));
+        assert_eq!(doc.failure(), Some(Failure::WrongPassword));
+        let lines = feed(Document::layout_extract, output);
+        let error = lines.iter().position(|l| l.starts_with("ERROR:")).unwrap();
+        assert!(lines[error + 1].starts_with("Wrong password"));
+        assert!(lines.contains(&"Enter password: ".to_string()));
+    }
+    #[test]
+    fn test_parse_dash_line_to_range() {
+        let ra = parse_dash_line_to_range("--- --- ---- ---- -----");
+        assert_eq!(ra, [0..3, 4..7, 8..12, 13..17, 18..23]);
+        let ra = parse_dash_line_to_range("--- --- ---- ----  -----");
+        assert_eq!(ra, [0..3, 4..7, 8..12, 13..17, 19..24]);
+        // no packed column, or only the name
+        let ra = parse_dash_line_to_range("--- --- ----  -----");
+        assert_eq!(ra, [0..3, 4..7, 8..12, 14..19]);
+        let ra = parse_dash_line_to_range("-----");
+        assert_eq!((ra.len(), ra[0].clone()), (1, 0..5));
+        let ra = parse_dash_line_to_range("--- --- ---- ---- ---- -----");
+        assert_eq!(ra.len(), 6);
+    }
+
+    #[test]
+    fn test_truncate_middle() {
+        assert_eq!(truncate_middle("a/b/c.png", 0), "a/b/c.png");
+        assert_eq!(truncate_middle("a/b/c.png", 9), "a/b/c.png");
+        assert_eq!(truncate_middle("a/b/c/d/e/f/z.png", 13), "a/b/c...z.png");
+        // wide chars are never split, the head takes what the tail left
+        let cut = truncate_middle("目录/子目录/文件.txt", 12);
+        assert_eq!(cut, "目录/....txt");
+        assert!(cut.chars().map(super::char_width).sum::<usize>() <= 12);
+
+        let tem = parse_dash_line_to_range(
+            "------------------- ----- ------------ ------------  ------------------------",
+        );
+        let line = FileLine::from((
+            "2023-12-12 09:18:24 ....A       344963       172481  test/deep/dir/01-e_01.png",
+            &tem,
+        ));
+        let full = line.to_string("", "", 0);
+        let short = line.to_string("", "", 12);
+        // the columns before the name are untouched
+        assert_eq!(full[..53], short[..53]);
+        assert!(short.ends_with("  test...1.png (49%)"));
+    }
+
+    #[test]
+    fn test_gzip_style_list() {
+        // a single member, only the name column
+        let output = r##"
+Name
+------------------------
+test.tar
+------------------------
+1 files
+"##;
+        let mut flb = FileListLB::default();
+        output.lines().for_each(|l| {
+            let _ = flb.input(l);
+        });
+        assert_eq!(flb.files(), vec!["test.tar"]);
+        assert_eq!(flb.summary().unwrap().file_count(), 1);
+
+        // no packed column
+        let output = r##"
+   Date      Time    Attr         Size   Name
+------------------- ----- ------------  ------------------------
+2023-12-22 16:17:58 .....      1263616  test.tar
+------------------- ----- ------------  ------------------------
+2023-12-22 16:17:58            1263616  1 files
+"##;
+        let mut flb = FileListLB::default();
+        output.lines().for_each(|l| {
+            let _ = flb.input(l);
+        });
+        assert_eq!(flb.files(), vec!["test.tar"]);
+        let summary = flb.summary().unwrap();
+        assert_eq!(summary.total_size(), 1263616);
+        assert_eq!(summary.total_packed(), None);
+
+        // a line ending before the name column, the trailing text is the name
+        let tem = flb.template.clone().unwrap();
+        let line = FileLine::from(("2023-12-22 16:17:58 .....  12  a.txt", &tem));
+        assert_eq!(line.filename, "a.txt");
+        assert_eq!(line.date, "2023-12-22 16:17:58");
+    }
+
+    #[test]
+    fn test_file_list_lb() {
+        let mut flb = FileListLB::default();
+        let raw = r##"
+------------------- ----- ------------ ------------  ------------------------
+2023-12-22 16:17:58 D....            0            0  test
+2023-12-12 09:18:24 ....A       344963     13216256  test/01-e_01.png
+2023-12-12 09:18:28 ....A       821434               test/02-e_02.png
+2023-12-12 09:18:26 ....A       608418               test/03-e_03.png
+2023-12-12 09:18:28 ....A       757826               test/04-e_04.png
+2023-12-12 09:18:28 ....A       790792               test/05-e_05.png
+2023-12-12 09:18:30 ....A       712878               test/06-e_06.png
+2023-12-12 09:18:30 ....A       740854               test/07-e_07.png
+2023-12-12 09:18:30 ....A       711147               test/08-e_08.png
+2023-12-12 09:18:32 ....A       724006               test/09-e_09.png
+2023-12-12 09:18:32 ....A       637246               test/10-e_10.png
+2023-12-12 09:18:32 ....A       739784               test/11-e_11.png
+2023-12-12 09:18:34 ....A       733386               test/12-e_12.png
+2023-12-12 09:18:34 ....A       683368               test/13-e_13.png
+2023-12-12 09:18:34 ....A       740540               test/14-e_14.png
+2023-12-12 09:18:38 ....A       781016               test/15-e_15.png
+2023-12-12 09:18:38 ....A       681962               test/16-e_16.png
+2023-12-12 09:18:38 ....A       830510               test/17-e_17.png
+2023-12-12 09:18:40 ....A       220106               test/18-e_18.png
+2023-12-12 09:18:40 ....A       436832               test/19-e_19.png
+2023-12-12 09:18:40 ....A       311853               test/20-e_20.png
+2023-12-12 09:18:42 ....A       328685               test/21-MT43.jpg
+2023-12-12 09:18:42 ....A          473               test/meta.json
+------------------- ----- ------------ ------------  ------------------------
+2023-12-22 16:17:58           13338079     13216256  22 files, 1 folders
+"##;
+        raw.lines().for_each(|l| {
+            let _ = flb.input(l);
+        });
+        let lb: Box<dyn LineBuilder> = Box::new(flb);
         lb.output().iter().for_each(|l| {
             println!("{}", l);
         });
@@ -537,6 +2530,949 @@ mod test {
         a.files().iter().for_each(|f| {
             println!("{}", f);
         });
+
+        let lines = lb.output();
+        let selected = a.select_files(&[lines[0].clone(), lines[2].clone(), lines[4].clone()]);
+        assert_eq!(selected, vec!["test/01-e_01.png", "test/03-e_03.png"]);
+
+        // preview the destination
+        let mut flb = FileListLB::default();
+        raw.lines().for_each(|l| {
+            let _ = flb.input(l);
+        });
+        assert!(flb.input("Set extract_path: /home/someone/download/"));
+        assert!(flb.output()[2].ends_with("/home/someone/download/test/01-e_01.png"));
+        assert!(flb.input("Set extract_path:"));
+        assert_eq!(flb.output(), lines);
+
+        let summary = flb.summary().unwrap();
+        assert_eq!(summary.total_size(), 13338079);
+        assert_eq!(summary.total_packed(), Some(13216256));
+        assert_eq!(summary.file_count(), 22);
+        assert_eq!(summary.folder_count(), 1);
+
+        // no packed column, no folders
+        let tem = flb.template.clone().unwrap();
+        let summary = ListSummary::from((
+            "2023-12-22 16:17:58                473                1 files",
+            &tem,
+        ));
+        assert_eq!(summary.total_size(), 473);
+        assert_eq!(summary.total_packed(), None);
+        assert_eq!(summary.file_count(), 1);
+        assert_eq!(summary.folder_count(), 0);
+    }
+
+    #[test]
+    fn test_encrypted_marker() {
+        let slt = r##"
+Path = /tmp/test.7z
+Type = 7z
+
+----------
+Path = secret.png
+Size = 344963
+Packed Size = 344963
+Modified = 2023-12-12 09:18:24
+Attributes = A
+CRC = 5B0A8B1C
+Encrypted = +
+Method = LZMA2:24 7zAES
+Block = 0
+
+Path = public.png
+Size = 821434
+Packed Size = 821434
+Modified = 2023-12-12 09:18:28
+Attributes = A
+CRC = 0C6A1C4B
+Encrypted = -
+Method = LZMA2:24
+Block = 1
+"##;
+        let list = r##"
+   Date      Time    Attr         Size   Compressed  Name
+------------------- ----- ------------ ------------  ------------------------
+2023-12-12 09:18:24 ....A       344963       344963  secret.png
+2023-12-12 09:18:28 ....A       821434       821434  public.png
+------------------- ----- ------------ ------------  ------------------------
+2023-12-12 09:18:28            1166397      1166397  2 files
+"##;
+        let mut doc = Document::new();
+        doc.layout_list();
+        list.lines().for_each(|l| doc.input(l));
+        // the columnar list alone does not tell it
+        assert!(!doc.output().iter().any(|l| l.starts_with(ENCRYPTED_MARKER)));
+
+        doc.layout_technical_list();
+        slt.lines().for_each(|l| doc.input(l));
+        // back to the columnar view, the flags from "l -slt" are kept
+        doc.layout_list();
+        list.lines().for_each(|l| doc.input(l));
+        let lines = doc.output();
+        let secret = lines.iter().position(|l| l.contains("secret.png")).unwrap();
+        assert!(lines[secret].starts_with("* 2023-12-12 09:18:24 ....A"));
+        assert!(lines[secret + 1].starts_with("2023-12-12 09:18:28 ....A"));
+        assert_eq!(
+            doc.select_files(&lines[secret..secret + 2]),
+            vec!["secret.png", "public.png"]
+        );
+    }
+
+    #[test]
+    fn test_file_line_ratio() {
+        let tem = parse_dash_line_to_range(
+            "------------------- ----- ------------ ------------  ------------------------",
+        );
+        let line = FileLine::from((
+            "2023-12-12 09:18:24 ....A       344963       172481  test/01-e_01.png",
+            &tem,
+        ));
+        assert_eq!(line.ratio(), Some(49));
+        assert!(line
+            .to_string("", "", 0)
+            .ends_with("test/01-e_01.png (49%)"));
+        // solid block, packed column is empty
+        let line = FileLine::from((
+            "2023-12-12 09:18:28 ....A       821434               test/02-e_02.png",
+            &tem,
+        ));
+        assert_eq!(line.ratio(), None);
+        assert!(line.to_string("", "", 0).ends_with("test/02-e_02.png"));
+        // folder, size is 0
+        let line = FileLine::from((
+            "2023-12-22 16:17:58 D....            0            0  test",
+            &tem,
+        ));
+        assert_eq!(line.ratio(), None);
+        assert!(line.to_string("", "", 0).ends_with("test"));
+    }
+
+    #[test]
+    fn test_human_size() {
+        assert_eq!(human_size(0), "0 B");
+        assert_eq!(human_size(1023), "1023 B");
+        assert_eq!(human_size(344963), "336.9 KiB");
+        assert_eq!(human_size(13338079), "12.7 MiB");
+        assert_eq!(human_size(5 * 1024 * 1024 * 1024), "5.0 GiB");
+        assert_eq!(human_size(2048 * 1024 * 1024 * 1024), "2048.0 GiB");
+
+        let mut flb = FileListLB {
+            human_size: true,
+            ..Default::default()
+        };
+        let raw = r##"   Date      Time    Attr         Size   Compressed  Name
+------------------- ----- ------------ ------------  ------------------------
+2023-12-22 16:17:58 D....            0            0  test
+2023-12-12 09:18:24 ....A       344963     13216256  test/01-e_01.png
+------------------- ----- ------------ ------------  ------------------------
+2023-12-22 16:17:58             344963     13216256  1 files, 1 folders"##;
+        raw.lines().for_each(|l| {
+            let _ = flb.input(l);
+        });
+        let lines = flb.output();
+        assert!(lines[0].ends_with("Compressed         Human  Name"));
+        assert!(lines[2].ends_with("0                test"));
+        assert!(lines[3].contains("13216256     336.9 KiB  test/01-e_01.png"));
+        assert!(lines[5].ends_with("13216256     336.9 KiB  1 files, 1 folders"));
+        assert_eq!(flb.files(), vec!["test", "test/01-e_01.png"]);
+    }
+
+    #[test]
+    fn test_sort_file_list() {
+        let mut flb = FileListLB::default();
+        let raw = r##"------------------- ----- ------------ ------------  ------------------------
+2023-12-12 09:18:30 ....A       740854               b.png
+2023-12-12 09:18:24 ....A       344963     13216256  c.png
+2023-12-12 09:18:28 ....A       740854               a.png
+------------------- ----- ------------ ------------  ------------------------
+2023-12-22 16:17:58            1826671     13216256  3 files"##;
+        raw.lines().for_each(|l| {
+            let _ = flb.input(l);
+        });
+        flb.sort(SortKey::Name);
+        assert_eq!(flb.files(), vec!["a.png", "b.png", "c.png"]);
+        // equal sizes keep the archive order
+        flb.sort(SortKey::Size);
+        assert_eq!(flb.files(), vec!["c.png", "b.png", "a.png"]);
+        flb.sort(SortKey::Date);
+        assert_eq!(flb.files(), vec!["c.png", "a.png", "b.png"]);
+        let lines = flb.output();
+        assert!(lines[0].starts_with("-----"));
+        assert!(lines[4].starts_with("-----"));
+        assert!(lines[5].ends_with("3 files"));
+        assert_eq!(SortKey::cycle(None), SortKey::Name);
+        assert_eq!(SortKey::cycle(Some(SortKey::Date)), SortKey::Name);
+    }
+
+    #[test]
+    fn test_error_lb_failure() {
+        let mut elb = ErrorLB::default();
+        assert!(elb.input("ERROR: Data Error : test/01-e_01.png"));
+        assert_eq!(elb.failure(), Some(Failure::Corrupt));
+        assert!(
+            elb.input("ERROR: Data Error in encrypted file. Wrong password? : test/02-e_02.png")
+        );
+        assert_eq!(elb.failure(), Some(Failure::WrongPassword));
+        // every error on its own row, then the hint
+        assert_eq!(
+            elb.output(),
+            vec![
+                "ERROR: Data Error : test/01-e_01.png",
+                "ERROR: Data Error in encrypted file. Wrong password? : test/02-e_02.png",
+                "Wrong password, edit the password line then try again",
+            ]
+        );
+
+        let mut elb = ErrorLB::default();
+        assert!(!elb.input("Everything is Ok"));
+        assert!(elb.input("ERROR: /tmp/test.7z : Can not open encrypted archive. Wrong password?"));
+        assert_eq!(elb.failure(), Some(Failure::WrongPassword));
+        assert!(elb.input("Password attempts: 2/3"));
+        assert!(elb.output()[1].contains("attempt 2"));
+        assert!(elb.input("Password attempts: 3/3"));
+        assert!(elb.output()[1].contains("archive corrupt"));
+    }
+
+    #[test]
+    fn test_clear_password_history() {
+        let file = std::env::temp_dir().join("vui-7z-test-clear-history.txt");
+        std::fs::write(&file, "123456\nhello").unwrap();
+        let mut plb = PasswordLB::new("space+x");
+        assert!(plb.input("Enter password (will not be echoed):"));
+        assert!(plb.input(&format!(
+            "Password history file: {}",
+            file.to_str().unwrap()
+        )));
+        assert!(plb.input("Input password: typed"));
+        assert_eq!(
+            plb.output(),
+            vec![
+                "Enter password: typed",
+                "select password use [space+x]: [1: 123456], [2: hello]"
+            ]
+        );
+        assert!(plb.input("Clear password history"));
+        assert_eq!(plb.output(), vec!["Enter password: typed"]);
+
+        let mut plb = PasswordLB::new("space+x");
+        assert!(plb.input("Enter password (will not be echoed):"));
+        assert!(plb.input("Mask password history"));
+        assert!(plb.input(&format!(
+            "Password history file: {}",
+            file.to_str().unwrap()
+        )));
+        assert_eq!(
+            plb.output()[1],
+            "select password use [space+x]: [1: ******], [2: *****]"
+        );
+        std::fs::remove_file(&file).unwrap();
+    }
+
+    #[test]
+    fn test_password_history_mru() {
+        let file = std::env::temp_dir().join("vui-7z-test-history-mru.txt");
+        std::fs::write(&file, "c\nb\na").unwrap();
+        let mut plb = PasswordLB::default();
+        assert!(plb.input("Enter password (will not be echoed):"));
+        assert!(plb.input("Password history limit: 3"));
+        assert!(plb.input(&format!(
+            "Password history file: {}",
+            file.to_str().unwrap()
+        )));
+        assert!(plb.input("Save password: a"));
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "a\nc\nb");
+        assert!(plb.input("Save password: d"));
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "d\na\nc");
+        std::fs::remove_file(&file).unwrap();
+    }
+
+    #[test]
+    fn test_progress_lb() {
+        let mut plb = ProgressLB::default();
+        assert_eq!(plb.output(), vec![""]);
+        assert!(plb.input("  0%"));
+        assert!(plb.input(" 45% 10 - test/01-e_01.png"));
+        assert!(!plb.input("Everything is Ok"));
+        assert!(!plb.input("Size:  100%"));
+        assert_eq!(plb.output(), vec!["Progress: 45%"]);
+
+        let mut doc = Document::new();
+        doc.layout_extract();
+        assert_eq!(doc.progress(), None);
+        doc.input(" 45% 10 - test/01-e_01.png");
+        let (line, content) = doc.progress().unwrap();
+        assert_eq!(content, "Progress: 45%");
+        assert_eq!(doc.output()[line as usize - 1], content);
+    }
+
+    #[test]
+    fn test_password_lb_input() {
+        let mut plb = PasswordLB::default();
+        assert!(plb.input("Enter password (will not be echoed):"));
+        assert_eq!(plb.output(), vec!["Enter password: "]);
+        assert!(plb.input("Input password: secret"));
+        assert_eq!(plb.output(), vec!["Enter password: secret"]);
+
+        // the password may be preset before 7z asks for it, init only once
+        let mut plb = PasswordLB::default();
+        assert!(plb.input("Input password: secret"));
+        assert!(plb.input("Enter password (will not be echoed):"));
+        assert!(plb.input("Input password: secret"));
+        assert_eq!(plb.output(), vec!["Enter password: secret"]);
+    }
+
+    #[test]
+    fn test_document_keeps_duplicate_rows() {
+        let mut doc = Document::new();
+        doc.layout_list();
+        let raw = r##"
+------------------- ----- ------------ ------------  ------------------------
+2023-12-12 09:18:24 ....A       344963       344963  test/01-e_01.png
+2023-12-12 09:18:24 ....A       344963       344963  test/01-e_01.png
+------------------- ----- ------------ ------------  ------------------------
+2023-12-12 09:18:24             689926       689926  2 files
+"##;
+        raw.lines().for_each(|l| doc.input(l));
+        let lines = doc.output();
+        let rows = lines.iter().filter(|l| l.contains("test/01-e_01.png"));
+        assert_eq!(rows.count(), 2);
+        assert!(!lines.windows(2).any(|w| w[0].is_empty() && w[1].is_empty()));
+    }
+
+    #[test]
+    fn test_empty_archive() {
+        let mut doc = Document::new();
+        doc.layout_list();
+        assert!(!doc.is_empty_archive());
+        let raw = r##"
+   Date      Time    Attr         Size   Compressed  Name
+------------------- ----- ------------ ------------  ------------------------
+------------------- ----- ------------ ------------  ------------------------
+                                 0            0  0 files
+"##;
+        raw.lines().for_each(|l| doc.input(l));
+        assert!(doc.files().is_empty());
+        assert!(doc.is_empty_archive());
+        assert!(doc.output().contains(&"Empty archive".to_string()));
+    }
+
+    #[test]
+    fn test_appending_files() {
+        let mut doc = Document::new();
+        doc.layout_list();
+        assert!(doc.appending_files(0).is_none());
+        doc.input("   Date      Time    Attr         Size   Compressed  Name");
+        doc.input("------------------- ----- ------------ ------------  ------------------------");
+        let (line, rows) = doc.appending_files(0).unwrap();
+        assert!(rows.is_empty());
+        doc.input("2023-12-12 09:18:24 ....A       344963       344963  test/01-e_01.png");
+        doc.input("2023-12-12 09:18:28 ....A       821434       821434  test/02-e_02.png");
+        let (second, rows) = doc.appending_files(1).unwrap();
+        assert_eq!(second, line + 1);
+        assert_eq!(rows.len(), 1);
+        // the rows are at the same lines in the full output
+        let lines = doc.output();
+        assert_eq!(lines[second as usize - 1], rows[0]);
+        doc.input("------------------- ----- ------------ ------------  ------------------------");
+        assert!(doc.appending_files(0).is_none());
+    }
+
+    #[test]
+    fn test_summary_lb() {
+        let mut doc = Document::new();
+        doc.layout_list();
+        let raw = r##"
+------------------- ----- ------------ ------------  ------------------------
+2023-12-22 16:17:58 D....            0            0  test
+2023-12-12 09:18:24 ....A     13338079     13216256  test/01-e_01.png
+------------------- ----- ------------ ------------  ------------------------
+2023-12-22 16:17:58           13338079     13216256  1 files, 1 folders
+"##;
+        raw.lines().for_each(|l| doc.input(l));
+        let lines = doc.output();
+        let at = lines.iter().position(|l| l.starts_with("-----")).unwrap();
+        assert_eq!(lines[at - 1], "1 file · 1 folder · 12.7 MiB");
+
+        let tem = parse_dash_line_to_range(
+            "------------------- ----- ------------ ------------  ------------------------",
+        );
+        let summary = ListSummary::from((
+            "                                 0            0  0 files",
+            &tem,
+        ));
+        let slb = SummaryLB {
+            summary: Some(summary),
+            filtered: None,
+        };
+        assert_eq!(slb.output(), vec!["Empty archive"]);
+        assert!(SummaryLB::default().output().is_empty());
+    }
+
+    #[test]
+    fn test_extract_mode_lb() {
+        let mut mlb = ExtractModeLB::default();
+        assert_eq!(
+            mlb.output(),
+            vec!["Extract mode: keep paths; overwrite existing"]
+        );
+        assert!(mlb.input("Set flatten: on"));
+        assert!(mlb.output()[0].starts_with("Extract mode: flat"));
+        assert!(mlb.input("Set flatten: off"));
+        mlb.overwrite = mlb.overwrite.cycle();
+        assert_eq!(mlb.overwrite.switch(), "-aos");
+        assert_eq!(
+            mlb.output(),
+            vec!["Extract mode: keep paths; skip existing"]
+        );
+        assert!(mlb.input("Set wrap dir: test"));
+        assert_eq!(
+            mlb.output(),
+            vec!["Extract mode: keep paths; into test/; skip existing"]
+        );
+        assert!(mlb.input("Set wrap dir: off"));
+        assert!(!mlb.output()[0].contains("into"));
+        assert!(!mlb.input("Extract to: /tmp"));
+    }
+
+    #[test]
+    fn test_volumes_lb() {
+        let mut doc = Document::new();
+        let head = doc.output().len();
+        doc.set_volumes(vec![
+            ("a.7z.001".to_string(), 5 * 1024 * 1024),
+            ("a.7z.002".to_string(), 5 * 1024 * 1024),
+            ("a.7z.010".to_string(), 2048),
+        ]);
+        assert!(doc.has_volumes());
+        let output = doc.output();
+        let at = output
+            .iter()
+            .position(|l| l.starts_with("Volumes"))
+            .unwrap();
+        assert_eq!(
+            output[at..at + 4],
+            [
+                "Volumes: 3 files, 10.0 MiB",
+                "  a.7z.001  5.0 MiB",
+                "  a.7z.002  5.0 MiB",
+                "  a.7z.010  2.0 KiB",
+            ]
+        );
+        doc.set_volumes(vec![]);
+        assert!(!doc.has_volumes());
+        assert_eq!(doc.output().len(), head);
+    }
+
+    #[test]
+    fn test_cancelled() {
+        let mut doc = Document::new();
+        doc.layout_extract();
+        doc.cancelled("extract", vec![]);
+        assert!(doc.output().contains(&"Cancelled: extract".to_string()));
+        doc.layout_extract();
+        assert!(!doc.output().iter().any(|l| l.starts_with("Cancelled")));
+        doc.cancelled("extract", vec!["/tmp/a/b.bin".to_string()]);
+        let output = doc.output();
+        let at = output
+            .iter()
+            .position(|l| l.starts_with("Cancelled"))
+            .unwrap();
+        assert_eq!(
+            output[at..at + 2],
+            [
+                "Cancelled: extract, 1 file written, may be partial",
+                "  /tmp/a/b.bin"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_checksum_lb() {
+        let mut clb = ChecksumLB::default();
+        assert!(!clb.input("Everything is Ok"));
+        assert!(clb.output().is_empty());
+        assert!(clb.input("Checksums: CRC32"));
+        assert!(clb.input("Checksum: dir/a.txt  CBF43926"));
+        assert_eq!(clb.output(), ["Checksum CRC32:", "  dir/a.txt  CBF43926"]);
+        // another pick replaces the rows
+        clb.input("Checksums: SHA-256");
+        assert_eq!(clb.output(), ["Checksum SHA-256:"]);
+    }
+
+    #[test]
+    fn test_warning_lb() {
+        let mut doc = Document::new();
+        doc.layout_extract();
+        doc.input("WARNING: Cannot open file a.png");
+        doc.input("WARNING: Cannot open file b.png");
+        doc.input("ERROR: Data Error : c.png");
+        let output = doc.output();
+        let at = |line: &str| output.iter().position(|l| l == line).unwrap();
+        // both warnings kept, apart from the error
+        assert_eq!(
+            at("WARNING: Cannot open file b.png"),
+            at("WARNING: Cannot open file a.png") + 1
+        );
+        assert!(at("ERROR: Data Error : c.png") > at("WARNING: Cannot open file b.png"));
+        let mut wlb = WarningLB::default();
+        assert!(!wlb.input("ERROR: Wrong password"));
+        assert!(wlb.output().is_empty());
+    }
+
+    #[test]
+    fn test_encryption_lb() {
+        let mut doc = Document::new();
+        doc.layout_add();
+        doc.input("Set encryption: headers");
+        assert!(doc
+            .output()
+            .contains(&"Encryption: files and headers, listing needs the password".to_string()));
+        doc.input("Set encryption: off");
+        assert!(!doc.output().iter().any(|l| l.starts_with("Encryption")));
+        let mut elb = EncryptionLB::default();
+        assert!(elb.input("Set encryption: files"));
+        assert_eq!(
+            elb.output(),
+            vec!["Encryption: files only, headers need the 7z format"]
+        );
+        assert!(!elb.input("Add file: test.zip"));
+    }
+
+    #[test]
+    fn test_conflicts() {
+        let mut doc = Document::new();
+        doc.layout_list();
+        let raw = r##"
+------------------- ----- ------------ ------------  ------------------------
+2023-12-22 16:17:58 D....            0            0  vui-7z-test-conflicts
+2023-12-12 09:18:24 ....A          473          473  vui-7z-test-conflicts/a.txt
+2023-12-12 09:18:24 ....A          473          473  vui-7z-test-conflicts/b.txt
+------------------- ----- ------------ ------------  ------------------------
+2023-12-22 16:17:58                946          946  2 files, 1 folders
+"##;
+        raw.lines().for_each(|l| doc.input(l));
+        let target = std::env::temp_dir();
+        let dir = target.join("vui-7z-test-conflicts");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("a.txt"), "a").unwrap();
+        // the folder row exists too, but it is not a conflict
+        assert_eq!(doc.conflicts(&target), vec![dir.join("a.txt")]);
+        assert!(doc.conflicts(&target.join("vui-7z-not-exists")).is_empty());
+        doc.input("Set flatten: on");
+        assert!(doc.conflicts(&target).is_empty());
+        assert_eq!(doc.conflicts(&dir), vec![dir.join("a.txt")]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_export_list() {
+        let mut doc = Document::new();
+        doc.layout_list();
+        let raw = r##"
+------------------- ----- ------------ ------------  ------------------------
+2023-12-22 16:17:58 D....            0            0  test
+2023-12-12 09:18:24 ....A          473          473  test/a.txt
+2023-12-12 09:18:24 ....A          473          473  test/b.png
+------------------- ----- ------------ ------------  ------------------------
+2023-12-22 16:17:58                946          946  2 files, 1 folders
+"##;
+        raw.lines().for_each(|l| doc.input(l));
+        assert_eq!(doc.export_list(None), "test\ntest/a.txt\ntest/b.png\n");
+        doc.filter("*.txt");
+        assert_eq!(
+            doc.export_list(Some(Path::new("/tmp/out"))),
+            "test/a.txt\t/tmp/out/test/a.txt\n"
+        );
+        doc.filter("");
+        doc.input("Set flatten: on");
+        assert_eq!(
+            doc.export_list(Some(Path::new("/tmp/out"))),
+            "test/a.txt\t/tmp/out/a.txt\ntest/b.png\t/tmp/out/b.png\n"
+        );
+    }
+
+    #[test]
+    fn test_banner_lb() {
+        let mut blb = BannerLB::default();
+        assert!(blb.output().is_empty());
+        assert!(!blb.input("Scanning the drive for archives: 7-Zip.7z"));
+        assert!(
+            blb.input("7-Zip (z) 23.01 (x64) : Copyright (c) 1999-2023 Igor Pavlov : 2023-06-20")
+        );
+        assert!(!blb.input("7-Zip (z) 23.01 (x64) : Copyright (c) 1999-2023 Igor Pavlov"));
+        assert_eq!(blb.output().len(), 1);
+
+        let mut doc = Document::new();
+        doc.layout_list();
+        doc.input("Extract file: /tmp/test.7z");
+        doc.input("Extract to: /tmp");
+        doc.input("7-Zip (z) 23.01 (x64) : Copyright (c) 1999-2023 Igor Pavlov : 2023-06-20");
+        doc.input("1 file, 13338079 bytes (13 MiB)");
+        doc.input("Enter password (will not be echoed):");
+        let lines = doc.output();
+        assert_eq!(lines[doc.password_line().unwrap() - 1], "Enter password: ");
+        assert!(lines.iter().any(|l| l.starts_with("7-Zip (z) 23.01")));
+    }
+
+    #[test]
+    fn test_password_line() {
+        let mut doc = Document::new();
+        doc.layout_list();
+        doc.input("Extract file: /tmp/test.7z");
+        doc.input("Extract to: /tmp");
+        doc.input("1 file, 13338079 bytes (13 MiB)");
+        assert_eq!(doc.password_line(), None);
+        doc.input("Enter password (will not be echoed):");
+        let list_line = doc.password_line().unwrap();
+        assert_eq!(doc.output()[list_line - 1], "Enter password: ");
+
+        // extract has more builders, the ones above the password are the same
+        doc.layout_extract();
+        doc.input("1 file, 13338079 bytes (13 MiB)");
+        doc.input("Enter password (will not be echoed):");
+        doc.input("Password history file: /tmp/vui-7z-not-exists.txt");
+        let extract_line = doc.password_line().unwrap();
+        assert_eq!(doc.output()[extract_line - 1], "Enter password: ");
+        assert_eq!(extract_line, list_line);
+    }
+
+    #[test]
+    fn test_comment_lb() {
+        let mut clb = CommentLB::default();
+        assert!(!clb.input("Type = zip"));
+        assert!(clb.output().is_empty());
+        assert!(clb.input("Comment = first line"));
+        assert!(clb.input("second line"));
+        assert!(!clb.input("Characteristics = Zip64"));
+        assert!(!clb.input("   Date      Time    Attr         Size   Compressed  Name"));
+        assert_eq!(
+            clb.output(),
+            vec!["Comment: first line", "         second line"]
+        );
+    }
+
+    #[test]
+    fn test_info_lb() {
+        let output = r##"
+7-Zip [64] 17.05 : Copyright (c) 1999-2021 Igor Pavlov : 2017-08-28
+
+Libs:
+ 0  /usr/lib/7zip/7z.so
+
+Formats:
+ 0 ...................   APFS     apfs img     00 00 00 00 00 00 00 00 4E 58 53 42
+ 0 CK...O.....XC.....    7z       7z           7z  '7z..'
+ 0 ...F...............   gzip     gz gzip tgz (.tar) tpz (.tar)  1F 8B 08
+ 0 ...................   Split    001
+
+Codecs:
+ 0 4ED   303011B BCJ2
+ 0  ED   6F10701 7zAES
+
+Hashers:
+ 0    4        1 CRC32
+"##;
+        let mut doc = Document::new();
+        doc.layout_info();
+        output.lines().for_each(|l| doc.input(l));
+        let lines = doc.output();
+        let at = lines
+            .iter()
+            .position(|l| l.starts_with("Formats ("))
+            .unwrap();
+        assert_eq!(lines[at], "Formats (4):");
+        assert!(lines[at + 1].starts_with("APFS (apfs img)"));
+        // the longest cell sets the width, two of them per row
+        assert!(lines[at + 2].starts_with("gzip (gz gzip tgz (.tar) tpz (.tar))"));
+        assert!(lines[at + 2].ends_with("Split (001)"));
+        assert!(lines.contains(&"Codecs (2):".to_string()));
+        assert!(lines
+            .iter()
+            .any(|l| l.starts_with("BCJ2") && l.contains("7zAES")));
+        assert!(!lines
+            .iter()
+            .any(|l| l.contains("CRC32") || l.contains("7z.so")));
+
+        assert_eq!(
+            InfoLB::parse_format(" 0 ...................   Split    001"),
+            Some(("Split".to_string(), "001".to_string()))
+        );
+        let cells = (0..30).map(|i| format!("c{}", i)).collect::<Vec<_>>();
+        let rows = InfoLB::columns(&cells);
+        assert!(rows.iter().all(|r| r.len() <= INFO_WIDTH));
+        assert!(rows.len() > 1);
+    }
+
+    #[test]
+    fn test_technical_list_lb() {
+        let raw = r##"
+Path = /tmp/test.7z
+Type = 7z
+
+----------
+Path = test/01-e_01.png
+Size = 344963
+Packed Size = 13216256
+Modified = 2023-12-12 09:18:24
+Attributes = A
+CRC = 5B0A8B1C
+Encrypted = +
+Method = LZMA2:24 7zAES
+
+Path = test/meta.json
+Size = 473
+CRC = 0C6A1C4B
+Encrypted = -
+"##;
+        let mut doc = Document::new();
+        doc.layout_technical_list();
+        raw.lines().for_each(|l| doc.input(l));
+        let lines = doc.output();
+        let first = lines
+            .iter()
+            .position(|l| l.starts_with("test/01-e_01.png"))
+            .unwrap();
+        assert_eq!(
+            lines[first],
+            "test/01-e_01.png  Size = 344963  CRC = 5B0A8B1C  Encrypted = + {{{1"
+        );
+        assert_eq!(lines[first + 1], "    Size = 344963");
+        assert_eq!(
+            lines[first + 8],
+            "test/meta.json  Size = 473  CRC = 0C6A1C4B  Encrypted = - {{{1"
+        );
+        assert_eq!(lines.len(), first + 12);
+
+        // back to the columnar view
+        doc.layout_list();
+        assert!(!doc.output().iter().any(|l| l.contains("{{{1")));
+
+        let mut tlb = TechnicalListLB::default();
+        assert!(!tlb.input("Path = /tmp/test.7z"));
+    }
+
+    #[test]
+    fn test_is_compressed_tar() {
+        let output = r##"
+   Date      Time    Attr         Size   Compressed  Name
+------------------- ----- ------------ ------------  ------------------------
+2023-12-22 16:17:58 .....      1263616       346618  test.tar
+------------------- ----- ------------ ------------  ------------------------
+2023-12-22 16:17:58            1263616       346618  1 files
+"##;
+        let mut doc = Document::new();
+        doc.layout_list();
+        doc.input("Extract file: /tmp/test.tar.gz");
+        output.lines().for_each(|l| doc.input(l));
+        assert!(doc.is_compressed_tar());
+
+        let mut doc = Document::new();
+        doc.layout_list();
+        doc.input("Extract file: /tmp/test.7z");
+        LIST_OUTPUT.lines().for_each(|l| doc.input(l));
+        assert!(!doc.is_compressed_tar());
+    }
+
+    #[test]
+    fn test_rename_target() {
+        let mut doc = Document::new();
+        doc.layout_list();
+        LIST_OUTPUT.lines().for_each(|l| doc.input(l));
+        let lines = doc.output();
+        let row_of = |name: &str| lines.iter().position(|l| l.contains(name)).unwrap() + 1;
+
+        let row = row_of("test/02-e_02.png");
+        let edited = lines[row - 1].replace("02-e_02.png", "renamed.png");
+        assert_eq!(
+            doc.rename_target(row, &edited),
+            Some((
+                "test/02-e_02.png".to_string(),
+                "test/renamed.png".to_string()
+            ))
+        );
+        assert_eq!(doc.rename_target(row, &lines[row - 1]), None);
+        // an emptied name is still a target, the rename refuses it
+        let emptied = lines[row - 1].replace("test/02-e_02.png", "");
+        assert_eq!(
+            doc.rename_target(row, &emptied).map(|(_, new)| new),
+            Some(String::new())
+        );
+        // the columns changed, or not a file row
+        let edited = lines[row - 1].replacen("2023", "1999", 1);
+        assert_eq!(doc.rename_target(row, &edited), None);
+        assert_eq!(doc.rename_target(1, "renamed"), None);
+
+        // the ratio is kept or dropped while editing, a non-solid list has one per file
+        let mut doc = Document::new();
+        doc.layout_list();
+        LIST_OUTPUT
+            .replace("821434               ", "821434       410717  ")
+            .lines()
+            .for_each(|l| doc.input(l));
+        let lines = doc.output();
+        let row = lines
+            .iter()
+            .position(|l| l.contains("test/01-e_01.png"))
+            .unwrap()
+            + 1;
+        let edited = lines[row - 1].replace("01-e_01.png", "renamed.png");
+        assert!(edited.ends_with("%)"));
+        let renamed = Some("test/renamed.png".to_string());
+        assert_eq!(doc.rename_target(row, &edited).map(|(_, n)| n), renamed);
+        let dropped = edited.rsplit_once(" (").unwrap().0;
+        assert_eq!(doc.rename_target(row, dropped).map(|(_, n)| n), renamed);
+    }
+
+    #[test]
+    fn test_file_path() {
+        let mut doc = Document::new();
+        doc.layout_list();
+        LIST_OUTPUT.lines().for_each(|l| doc.input(l));
+        let lines = doc.output();
+        let row = |name: &str| lines.iter().find(|l| l.ends_with(name)).unwrap();
+        assert_eq!(doc.file_path(row("  test")), Some("test/".to_string()));
+        let png = lines.iter().find(|l| l.contains("01-e_01.png")).unwrap();
+        assert_eq!(doc.file_path(png), Some("test/01-e_01.png".to_string()));
+        assert_eq!(doc.file_path(&lines[0]), None);
+    }
+
+    #[test]
+    fn test_find_file() {
+        let mut doc = Document::new();
+        doc.layout_list();
+        LIST_OUTPUT.lines().for_each(|l| doc.input(l));
+        let lines = doc.output();
+        let first = doc.find_file("PNG", 0).unwrap();
+        // the head of a solid block, its packed size is the one of the whole block
+        assert!(lines[first - 1].ends_with("test/01-e_01.png"));
+        let second = doc.find_file("png", first).unwrap();
+        assert!(lines[second - 1].ends_with("test/02-e_02.png"));
+        // wraps around
+        assert_eq!(doc.find_file("png", second), Some(first));
+        assert_eq!(doc.find_file("*.jpg", 0), None);
+    }
+
+    #[test]
+    fn test_toggle_hidden() {
+        let mut doc = Document::new();
+        doc.layout_list();
+        let raw = r##"
+------------------- ----- ------------ ------------  ------------------------
+2023-12-12 09:18:24 ....A          100          100  test/a.txt
+2023-12-12 09:18:24 ....A          100          100  test/.env
+2023-12-12 09:18:24 ....A          100          100  .git/config
+2023-12-12 09:18:24 ..H.A          100          100  test/thumbs.db
+2023-12-12 09:18:24 ...SA          100          100  test/desktop.ini
+------------------- ----- ------------ ------------  ------------------------
+2023-12-22 16:17:58                500          500  5 files
+"##;
+        raw.lines().for_each(|l| doc.input(l));
+        assert!(doc.toggle_hidden());
+        let lines = doc.output();
+        assert!(lines.iter().any(|l| l.contains("test/a.txt")));
+        for hidden in [".env", ".git/config", "thumbs.db", "desktop.ini"] {
+            assert!(!lines.iter().any(|l| l.contains(hidden)), "{}", hidden);
+        }
+        assert!(lines.contains(&"5 files · 500 B · 1 shown, hidden skipped".to_string()));
+        doc.filter("*.txt");
+        assert!(doc
+            .output()
+            .contains(&"5 files · 500 B · 1 shown for \"*.txt\", hidden skipped".to_string()));
+        doc.filter("");
+        assert!(!doc.toggle_hidden());
+        assert!(doc.output().iter().any(|l| l.contains("test/.env")));
+        // kept when listing again
+        doc.toggle_hidden();
+        doc.layout_list();
+        raw.lines().for_each(|l| doc.input(l));
+        assert!(!doc.output().iter().any(|l| l.contains("test/.env")));
+    }
+
+    #[test]
+    fn test_filter() {
+        assert!(matches_filter("png", "test/01-E_01.PNG"));
+        assert!(matches_filter("*.png", "test/01-e_01.png"));
+        assert!(matches_filter("test/0?-*", "test/02-e_02.png"));
+        assert!(!matches_filter("*.jpg", "test/01-e_01.png"));
+        assert!(!matches_filter("?est", "test/01-e_01.png"));
+
+        let mut doc = Document::new();
+        doc.layout_list();
+        LIST_OUTPUT.lines().for_each(|l| doc.input(l));
+        doc.filter("*02*");
+        let lines = doc.output();
+        assert!(lines.contains(&"2 files · 1 folder · 1.1 MiB · 1 shown for \"*02*\"".to_string()));
+        assert!(lines.iter().any(|l| l.ends_with("test/02-e_02.png")));
+        assert!(!lines.iter().any(|l| l.contains("test/01-e_01.png")));
+        // the summary row of 7z stays
+        assert_eq!(lines.last().unwrap(), &LIST_OUTPUT.lines().last().unwrap());
+
+        // kept when listing again, then cleared
+        doc.layout_list();
+        LIST_OUTPUT.lines().for_each(|l| doc.input(l));
+        assert!(!doc.output().iter().any(|l| l.contains("test/01-e_01.png")));
+        doc.filter(" ");
+        assert!(doc.output().iter().any(|l| l.contains("test/01-e_01.png")));
+    }
+
+    #[test]
+    fn test_to_json() {
+        let mut doc = Document::new();
+        doc.layout_list();
+        LIST_OUTPUT.lines().for_each(|l| doc.input(l));
+        doc.sort(SortKey::Size);
+        let json = serde_json::from_str::<serde_json::Value>(&doc.to_json()).unwrap();
+        let files = json.as_array().unwrap();
+        assert_eq!(files.len(), 3);
+        // archive order, not the sorted one
+        assert_eq!(files[0]["name"], "test");
+        assert_eq!(files[0]["attributes"], "D....");
+        assert_eq!(files[1]["name"], "test/01-e_01.png");
+        assert_eq!(files[1]["size"], 344963);
+        assert_eq!(files[1]["packed"], 13216256);
+        assert_eq!(files[1]["date"], "2023-12-12 09:18:24");
+        assert!(files[2]["packed"].is_null());
+    }
+
+    #[test]
+    fn test_footer_lb() {
+        let mut doc = Document::new();
+        doc.set_keys(key_labels(&keymaps(&HashMap::new()).unwrap()));
+        doc.layout_list();
+        doc.input("Extract file: /tmp/test.7z");
+        doc.input("Extract to: /tmp/test");
+        assert_eq!(doc.footer(), "Keys: [space+q] quit");
+        doc.input("Set context: list");
+        assert!(doc.footer().starts_with("Keys: [space+c] extract"));
+        assert_eq!(doc.output()[FOOTER_LINE as usize - 1], doc.footer());
+        assert_eq!(doc.output()[STATUS_LINE as usize - 1], doc.status());
+        doc.input("Set context: password");
+        assert!(doc.footer().contains("[space+x] select saved password"));
+        assert!(doc.output()[0].starts_with("7Z-VUI, Shortcuts: `space+c`: execute"));
+
+        // remapped in [keymaps]
+        let overrides = HashMap::from([("execute".to_string(), "<C-e>".to_string())]);
+        doc.set_keys(key_labels(&keymaps(&overrides).unwrap()));
+        doc.input("Set context: list");
+        assert!(doc.footer().starts_with("Keys: [Ctrl+e] extract"));
+        assert!(doc.output()[0].contains("`Ctrl+e`: execute"));
+        assert!(!doc.output()[0].contains("space+c"));
+    }
+
+    #[test]
+    fn test_status_lb() {
+        let mut slb = StatusLB::default();
+        assert_eq!(slb.output(), vec!["Status: idle"]);
+        assert!(slb.input("Status: running list"));
+        assert_eq!(slb.output(), vec!["Status: running list |"]);
+        for _ in 0..SPINNER_STEP {
+            assert!(!slb.input("2023-01-01 00:00:00 ....A  1  1  a.txt"));
+        }
+        assert_eq!(slb.output(), vec!["Status: running list /"]);
+        assert!(slb.input("Status: idle"));
+        assert!(!slb.input("Everything is Ok"));
+        assert_eq!(slb.output(), vec!["Status: idle"]);
     }
 
     #[test]
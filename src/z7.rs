@@ -1,36 +1,42 @@
 use std::{
-    ffi::OsStr,
-    io::ErrorKind,
-    path::PathBuf,
+    collections::{HashMap, VecDeque},
+    ffi::{OsStr, OsString},
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
     process::{ExitStatus, Stdio},
-    str::from_utf8,
     sync::Arc,
+    time::{Duration, Instant, SystemTime},
     vec,
 };
 
-use log::{error, info};
+use log::{error, info, warn};
+use tempfile::TempDir;
 use tokio::{
-    io::{AsyncReadExt, AsyncWriteExt},
+    io::{AsyncBufReadExt, AsyncRead, AsyncWriteExt, BufReader},
     process::{Child, ChildStdin, Command},
     select,
     sync::{
         mpsc::{self},
-        RwLock,
+        oneshot, RwLock,
     },
+    time::sleep,
     try_join,
 };
 
 use crate::{
+    checksum::{file_checksum, HashKind},
     options::Options,
-    output_format::{Document, PASSWORD_LINE},
+    output_format::{Document, Failure, OverwriteMode, SortKey, FOOTER_LINE, STATUS_LINE},
 };
 
 #[derive(Debug)]
 pub enum Pushment {
     // the option is (col, row), for nvim cursor
     Full(Vec<String>, Option<(usize, usize)>),
-    #[allow(dead_code)]
+    // replace a single line, the number starts from 1
     Line(u64, String),
+    // the whole detail pane, properties of the file under the cursor
+    Detail(Vec<String>),
     #[allow(dead_code)]
     None,
 }
@@ -38,36 +44,234 @@ pub enum Pushment {
 #[derive(Debug)]
 pub enum Operation {
     Password(String),
+    // a numbered choice from the password history, resolved to its password: written to stdin if 7z waits on the prompt now,
+    // otherwise queued in selected_password and listed again unless a command is running,
+    // read_document answers the next "Enter password" with the queued one
     SelectPassword(String),
     ExtractTo(String),
+    // lines selected in nvim, resolved to archive paths by the document
+    ExtractFiles(Vec<String>),
+    // paths on the filesystem that will be added to the archive,
+    // with the password to encrypt them, empty for no encryption
+    Add(Vec<String>, Option<String>),
+    // lines selected in nvim, resolved to archive paths by the document
+    Delete(Vec<String>),
+    // the row and the line edited in nvim, answered with (old, new) if a file name changed
+    RenameTarget(usize, String, oneshot::Sender<Option<(String, String)>>),
+    // archive paths, the new one must not be empty or taken
+    Rename { old: String, new: String },
+    // push the whole document again, edits in the buffer are dropped
+    Render,
+    // the cursor moved to the line, its file shows in the detail pane
+    FileDetail(String),
+    Sort(SortKey),
+    // an empty pattern shows every file again
+    Filter(String),
+    // dotfiles and hidden or system entries are left out of the list
+    ToggleHidden,
+    // query and the cursor row, jump to the next matching file row after it
+    FindFile(String, usize),
+    PreviewExtract,
+    // switch between the columnar list and "l -slt"
+    TechnicalList,
+    // extract without the paths in archive, 7z "e" instead of "x"
+    ToggleFlatten,
+    // add or remove the archive stem at the end of extract_to_path
+    ToggleWrapDir,
+    SetOverwriteMode(OverwriteMode),
+    // open extract_to_path with the opener, only after a successful extract
+    OpenDestination,
+    // "path  hash" of the files found in extract_to_path, only after a successful extract
+    ChecksumExtracted(HashKind),
+    ClearHistory,
+    // a rendered line, answered with its archive path, folders end with '/'
+    FilePath(String, oneshot::Sender<Option<String>>),
+    // write the parsed file list as JSON after the running command, "-" is stdout
+    ExportJson(PathBuf),
+    // the shown file names one per line, and their extract destinations if true
+    ExportList(PathBuf, bool),
+    // "7z i" into the document, read only
+    ShowFormats,
+    // show or hide the split volumes next to the archive, found on the filesystem
+    ShowVolumes,
+    // wait for the running command, then quit nvim
+    Quit,
     Execute,
+    Test,
     Retry,
+    // list again with the current password, Retry forgets it
+    Refresh,
+    // kill the running 7z, the files it wrote so far stay
+    Cancel,
+    // extract the selected lines, or everything if none, to a temp dir and open it
+    Peek(Vec<String>),
+    // a rendered line, browse the archive file of it like the outer one
+    EnterArchive(String),
+    // back to the outer archive, the temp dir of the nested one is removed
+    LeaveArchive,
 }
 
 #[derive(Debug)]
 pub enum Cmd {
     List,
     Extract,
+    ExtractFiles(Vec<String>),
+    // .tar.gz and alike, decompress to a temp dir then unpack the tar
+    ExtractTar,
+    Add(Vec<String>),
+    Delete(Vec<String>),
+    // "rn", old and new archive path
+    Rename(String, String),
+    Test,
+    // "l -slt", properties of every file
+    TechnicalList,
+    // "i", formats and codecs of the 7z binary
+    Info,
+    // extract a nested archive to a temp dir, it is listed in place of this one then
+    Enter(String),
+}
+
+impl Cmd {
+    fn name(&self) -> &'static str {
+        match self {
+            Cmd::List => "list",
+            Cmd::Extract => "extract",
+            Cmd::ExtractFiles(_) => "extract selected",
+            Cmd::ExtractTar => "extract tar",
+            Cmd::Add(_) => "add",
+            Cmd::Delete(_) => "delete",
+            Cmd::Rename(_, _) => "rename",
+            Cmd::Test => "test",
+            Cmd::TechnicalList => "technical list",
+            Cmd::Info => "info",
+            Cmd::Enter(_) => "enter archive",
+        }
+    }
 }
 
 #[derive(Debug)]
 pub enum ExecuteStatus {
     List(ExitStatus),
+    // a successful extract is kept, the destination can be opened then
     Extract(ExitStatus),
+    Test(ExitStatus),
+    Add(ExitStatus),
+    Delete(ExitStatus),
+    Rename(ExitStatus),
+    // "7z i", a failure still tells in the exit code
+    Info(ExitStatus),
     None,
     Pedding,
 }
 
+impl ExecuteStatus {
+    /// headers encrypted archive, 'list' failed with a wrong password,
+    /// a new password can only be tried by listing again
+    fn relist_with_password(&self) -> bool {
+        matches!(self, ExecuteStatus::List(status) if !status.success())
+    }
+
+    /// a selected password goes to stdin only if the running 7z waits on the prompt,
+    /// the pipe is taken on the first write, a finished command has none
+    fn write_selected_password(&self, waiting_stdin: bool) -> bool {
+        matches!(self, ExecuteStatus::Pedding) && waiting_stdin
+    }
+
+    fn extracted(&self) -> bool {
+        matches!(self, ExecuteStatus::Extract(status) if status.success())
+    }
+
+    /// a failed command is told apart by the failure the document detected
+    fn exit_code(&self, failure: Option<Failure>) -> ExitCode {
+        let status = match self {
+            ExecuteStatus::None => return ExitCode::Success,
+            ExecuteStatus::Pedding => return ExitCode::Quit,
+            ExecuteStatus::List(status)
+            | ExecuteStatus::Extract(status)
+            | ExecuteStatus::Test(status)
+            | ExecuteStatus::Add(status)
+            | ExecuteStatus::Delete(status)
+            | ExecuteStatus::Rename(status)
+            | ExecuteStatus::Info(status) => status,
+        };
+        match failure {
+            _ if status.success() => ExitCode::Success,
+            Some(Failure::WrongPassword) => ExitCode::WrongPassword,
+            Some(Failure::Corrupt) => ExitCode::Corrupt,
+            None => ExitCode::Failed,
+        }
+    }
+}
+
+/// the process exit code, from the last 7z command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitCode {
+    Success = 0,
+    // 7z failed for another reason, also used for errors before starting
+    Failed = 1,
+    WrongPassword = 2,
+    // data error, crc failed or not an archive
+    Corrupt = 3,
+    // nvim quit while a command was still running
+    Quit = 4,
+}
+
 pub struct Z7 {
     document: Arc<RwLock<Document>>,
     doc_sender: mpsc::Sender<Pushment>,
     password: Arc<RwLock<Option<String>>>,
     selected_password: Arc<RwLock<Option<String>>>,
     stdin_pipe: Arc<RwLock<Option<ChildStdin>>>,
+    // the running 7z process, killed when a new command replaces it
+    running: Arc<RwLock<Option<Child>>>,
     execute_status: Arc<RwLock<ExecuteStatus>>,
-    file: String,
+    // the archive being listed, a nested one extracted to a temp dir once entered
+    file: Arc<RwLock<String>>,
+    // relative extract paths are resolved from here
+    archive_dir: PathBuf,
     extract_to_path: Arc<RwLock<PathBuf>>,
+    flatten: Arc<RwLock<bool>>,
+    // the archive stem was pushed to extract_to_path by us, not typed by the user
+    wrap_dir: Arc<RwLock<bool>>,
+    technical_list: Arc<RwLock<bool>>,
+    overwrite: Arc<RwLock<OverwriteMode>>,
     password_history_file: String,
+    seven_zip_bin: String,
+    // --7z-arg, passed to every 7z command
+    extra_args: Vec<String>,
+    // "-scsUTF-8" for the commands printing file names
+    force_utf8: bool,
+    // --confirm-execute, the first execute only asks for a second one
+    confirm_execute: bool,
+    opener: String,
+    // where the last extract directory is saved, None if not remember
+    last_extract_path_file: Option<PathBuf>,
+    mask_password: bool,
+    // the stars last put on the password line, nvim sends them back when leaving insert mode
+    masked_password: Arc<RwLock<Option<String>>>,
+    history_limit: usize,
+    // wrong passwords in a row, reset on success
+    password_attempts: Arc<RwLock<usize>>,
+    max_password_attempts: usize,
+    // passwords from --try-passwords not tried yet, and the one being tried
+    candidates: Arc<RwLock<VecDeque<String>>>,
+    candidate: Arc<RwLock<Option<String>>>,
+    // "l -slt" lines of the files shown in the detail pane, by archive path
+    details: Arc<RwLock<HashMap<String, Vec<String>>>>,
+    // bumped on every cursor move, a slower answer for an older line is dropped
+    detail_seq: Arc<RwLock<usize>>,
+    // exports asked for while a command runs, made once it finished
+    pending_exports: Arc<RwLock<Vec<Operation>>>,
+    // the last list without a password, replayed while the archive is unchanged
+    list_cache: Arc<RwLock<Option<ListCache>>>,
+    // the running 7z was killed by Cancel, its exit is not a failure
+    cancelled: Arc<RwLock<bool>>,
+    // the outer archives, the last one is restored when leaving a nested archive
+    archives: Arc<RwLock<Vec<ArchiveContext>>>,
+    // temp dirs of the peeks, kept until quit, the opener may still show them
+    peek_dirs: Arc<RwLock<Vec<TempDir>>>,
+    // the destination a running peek replaced, restored when its extract is done
+    peeking: Arc<RwLock<Option<PathBuf>>>,
 }
 
 impl Clone for Z7 {
@@ -78,10 +282,37 @@ impl Clone for Z7 {
             password: self.password.clone(),
             selected_password: self.selected_password.clone(),
             stdin_pipe: self.stdin_pipe.clone(),
+            running: self.running.clone(),
             execute_status: self.execute_status.clone(),
             file: self.file.clone(),
+            archive_dir: self.archive_dir.clone(),
             extract_to_path: self.extract_to_path.clone(),
+            flatten: self.flatten.clone(),
+            wrap_dir: self.wrap_dir.clone(),
+            technical_list: self.technical_list.clone(),
+            overwrite: self.overwrite.clone(),
             password_history_file: self.password_history_file.clone(),
+            seven_zip_bin: self.seven_zip_bin.clone(),
+            extra_args: self.extra_args.clone(),
+            force_utf8: self.force_utf8,
+            confirm_execute: self.confirm_execute,
+            opener: self.opener.clone(),
+            last_extract_path_file: self.last_extract_path_file.clone(),
+            mask_password: self.mask_password,
+            masked_password: self.masked_password.clone(),
+            history_limit: self.history_limit,
+            password_attempts: self.password_attempts.clone(),
+            max_password_attempts: self.max_password_attempts,
+            candidates: self.candidates.clone(),
+            candidate: self.candidate.clone(),
+            details: self.details.clone(),
+            detail_seq: self.detail_seq.clone(),
+            pending_exports: self.pending_exports.clone(),
+            list_cache: self.list_cache.clone(),
+            cancelled: self.cancelled.clone(),
+            archives: self.archives.clone(),
+            peek_dirs: self.peek_dirs.clone(),
+            peeking: self.peeking.clone(),
         }
     }
 }
@@ -89,18 +320,71 @@ impl Clone for Z7 {
 impl Z7 {
     pub fn new(pusher: mpsc::Sender<Pushment>, opt: &Options) -> Self {
         let file = opt.file.file.clone();
-        let extract_to_path = PathBuf::from(PathBuf::from(&file).parent().unwrap());
         let password_history_file = opt.password_history_file.clone();
+        let last_extract_path_file = (!opt.no_remember_path)
+            .then(|| PathBuf::from(&password_history_file).with_file_name(LAST_EXTRACT_PATH_FILE));
+        // --output-dir wins over the remembered one
+        let extract_to_path = output_dir(opt)
+            .or_else(|| {
+                last_extract_path_file
+                    .as_ref()
+                    .and_then(|f| load_last_extract_path(f))
+            })
+            .unwrap_or_else(|| opt.file.dir());
+        let seven_zip_bin = opt.seven_zip_bin.clone();
+        let mut document = Document::new();
+        document.set_human_size(opt.human_size);
+        document.set_max_name_width(opt.max_name_width);
+        let mut candidates = opt
+            .try_passwords
+            .as_ref()
+            .map(|f| load_candidates(f))
+            .unwrap_or_default();
+        // the preset password goes first, candidates are tried after it failed
+        let candidate = opt
+            .password
+            .is_none()
+            .then(|| candidates.pop_front())
+            .flatten();
+        let password = opt.password.clone().or(candidate.clone());
         Self {
-            document: Arc::new(RwLock::new(Document::new())),
+            document: Arc::new(RwLock::new(document)),
             doc_sender: pusher,
-            password: Arc::new(RwLock::new(None)),
+            // a wrong preset password is cleared on failure, then prompt as usual
+            password: Arc::new(RwLock::new(password)),
             selected_password: Arc::new(RwLock::new(None)),
             stdin_pipe: Arc::new(RwLock::new(None)),
+            running: Arc::new(RwLock::new(None)),
             execute_status: Arc::new(RwLock::new(ExecuteStatus::None)),
-            file,
+            file: Arc::new(RwLock::new(file)),
+            archive_dir: opt.file.dir(),
             extract_to_path: Arc::new(RwLock::new(extract_to_path)),
+            flatten: Arc::new(RwLock::new(false)),
+            wrap_dir: Arc::new(RwLock::new(false)),
+            technical_list: Arc::new(RwLock::new(false)),
+            overwrite: Arc::new(RwLock::new(OverwriteMode::default())),
             password_history_file,
+            seven_zip_bin,
+            extra_args: opt.seven_zip_args.clone(),
+            force_utf8: !opt.no_force_utf8,
+            confirm_execute: opt.confirm_execute,
+            opener: opt.opener.clone(),
+            last_extract_path_file,
+            mask_password: opt.mask_password,
+            masked_password: Arc::new(RwLock::new(None)),
+            history_limit: opt.history_limit,
+            password_attempts: Arc::new(RwLock::new(0)),
+            max_password_attempts: opt.max_password_attempts,
+            candidates: Arc::new(RwLock::new(candidates)),
+            candidate: Arc::new(RwLock::new(candidate)),
+            details: Arc::new(RwLock::new(HashMap::new())),
+            detail_seq: Arc::new(RwLock::new(0)),
+            pending_exports: Arc::new(RwLock::new(vec![])),
+            list_cache: Arc::new(RwLock::new(None)),
+            cancelled: Arc::new(RwLock::new(false)),
+            archives: Arc::new(RwLock::new(vec![])),
+            peek_dirs: Arc::new(RwLock::new(vec![])),
+            peeking: Arc::new(RwLock::new(None)),
         }
     }
 
@@ -116,69 +400,601 @@ impl Z7 {
         cmd_sender.send(Cmd::List).await.expect("cmd sender error");
 
         let doc_sender_wait_close = self.doc_sender.clone();
+        let running = self.running.clone();
 
+        // nvim is gone, 7z would keep writing to nobody, kill it before canceling others
         let wait_doc_sender_closed = async move {
             doc_sender_wait_close.closed().await;
             info!("doc channel closed");
+            kill_running(&running).await;
             tokio::io::Result::<()>::Err(ErrorKind::Other.into())
         };
         let mut z7_1 = self.clone();
         let mut z7_2 = self.clone();
         try_join!(
-            z7_1.operation_make(cmd_sender, oper_recv),
-            z7_2.executing_cmd(cmd_recv, opt_sender),
+            z7_1.operation_make(cmd_sender.clone(), oper_recv),
+            z7_2.executing_cmd(cmd_recv, cmd_sender, opt_sender),
             self.read_document(opt_recv, oper_sender),
             wait_doc_sender_closed
         )
         .map(|_| ())
     }
 
+    /// the keys of the keymaps, remapped ones included, for the hints in the document
+    pub async fn set_keys(&self, keys: HashMap<String, String>) {
+        self.document.write().await.set_keys(keys);
+    }
+
+    /// read before shutdown, a killed command would look like a failure
+    pub async fn exit_code(&self) -> ExitCode {
+        let failure = self.document.read().await.failure();
+        self.execute_status.read().await.exit_code(failure)
+    }
+
+    /// kill the running 7z, start may be canceled with a child still running,
+    /// the temp dirs of entered archives are removed
+    pub async fn shutdown(&self) {
+        kill_running(&self.running).await;
+        for context in self.archives.write().await.drain(..) {
+            remove_temp_dir(context.temp_dir);
+        }
+        // dropping the handles removes the peek dirs
+        self.peek_dirs.write().await.clear();
+    }
+
+    /// the extract of a peek is done, put the chosen destination back, the peek dir is returned
+    async fn end_peek(&self) -> Option<PathBuf> {
+        let previous = self.peeking.write().await.take()?;
+        let dir = std::mem::replace(&mut *self.extract_to_path.write().await, previous.clone());
+        let input = format!("Extract to: {}", previous.display());
+        self.document.write().await.input(&input);
+        if let Err(e) = self.doc_sender.send(Pushment::Line(4, input)).await {
+            info!("pushment sender error: {}", e);
+        }
+        Some(dir)
+    }
+
+    /// the nested archive was extracted, remember the outer one and browse the nested
+    async fn push_archive(&self, outer: &str, member: &str, temp_dir: TempDir) {
+        let mut archives = self.archives.write().await;
+        let inner = temp_dir
+            .path()
+            .join(Path::new(member).file_name().unwrap_or_default());
+        *self.file.write().await = inner.to_string_lossy().to_string();
+        archives.push(ArchiveContext {
+            file: outer.to_string(),
+            password: self.password.write().await.take(),
+            extract_to_path: self.extract_to_path.read().await.clone(),
+            wrap_dir: *self.wrap_dir.read().await,
+            temp_dir,
+        });
+        self.details.write().await.clear();
+        info!("entered {}, depth {}", member, archives.len());
+    }
+
+    async fn restore_archive(&self, context: ArchiveContext) {
+        *self.file.write().await = context.file;
+        *self.password.write().await = context.password;
+        *self.extract_to_path.write().await = context.extract_to_path;
+        *self.wrap_dir.write().await = context.wrap_dir;
+        self.details.write().await.clear();
+        remove_temp_dir(context.temp_dir);
+    }
+
     pub async fn operation_make(
         &mut self,
         cmd_sender: mpsc::Sender<Cmd>,
         mut oper_recv: mpsc::Receiver<Operation>,
     ) -> tokio::io::Result<()> {
+        let mut confirm = ExecuteConfirm::default();
         while let Some(oper) = oper_recv.recv().await {
             info!("recv operation: {:?}", oper);
             match oper {
                 Operation::Execute => {
-                    if let Err(e) = cmd_sender.send(Cmd::Extract).await {
+                    if self.confirm_execute && !confirm.confirm(Instant::now()) {
+                        let extract_to_path = self.extract_to_path.read().await.clone();
+                        let key = self.document.read().await.key("execute");
+                        self.push_status(&format!(
+                            "press {} again within {}s to extract to {}",
+                            key,
+                            CONFIRM_TIMEOUT.as_secs(),
+                            extract_to_path.display()
+                        ))
+                        .await;
+                        continue;
+                    }
+                    if self.document.read().await.is_empty_archive() {
+                        self.push_status("idle, the archive is empty, nothing to extract")
+                            .await;
+                        continue;
+                    }
+                    let cmd = if self.document.read().await.is_compressed_tar() {
+                        Cmd::ExtractTar
+                    } else {
+                        Cmd::Extract
+                    };
+                    if let Err(e) = cmd_sender.send(cmd).await {
+                        error!("send cmd error: {}", e);
+                        return Err(ErrorKind::BrokenPipe.into());
+                    }
+                }
+                Operation::Peek(lines) => {
+                    if matches!(*self.execute_status.read().await, ExecuteStatus::Pedding) {
+                        self.push_status("busy, peek after the running command")
+                            .await;
+                        continue;
+                    }
+                    if self.document.read().await.is_empty_archive() {
+                        self.push_status("idle, the archive is empty, nothing to peek")
+                            .await;
+                        continue;
+                    }
+                    let cmd = if lines.is_empty() {
+                        if self.document.read().await.is_compressed_tar() {
+                            Cmd::ExtractTar
+                        } else {
+                            Cmd::Extract
+                        }
+                    } else {
+                        let files = self.document.read().await.select_files(&lines);
+                        if files.is_empty() {
+                            info!("no file selected in lines: {:?}", lines);
+                            continue;
+                        }
+                        Cmd::ExtractFiles(files)
+                    };
+                    let dir = match temp_dir("vui-7z-peek-") {
+                        Ok(dir) => {
+                            let path = dir.path().to_path_buf();
+                            self.peek_dirs.write().await.push(dir);
+                            path
+                        }
+                        Err(e) => {
+                            error!("create peek dir error: {}", e);
+                            self.push_status(&format!("peek failed, {}", e)).await;
+                            continue;
+                        }
+                    };
+                    // not saved as the last extract path, it is gone after quit
+                    let previous =
+                        std::mem::replace(&mut *self.extract_to_path.write().await, dir.clone());
+                    self.peeking.write().await.get_or_insert(previous);
+                    self.document
+                        .write()
+                        .await
+                        .input(&format!("Extract to: {}", dir.display()));
+                    if let Err(e) = cmd_sender.send(cmd).await {
+                        error!("send cmd error: {}", e);
+                        return Err(ErrorKind::BrokenPipe.into());
+                    }
+                }
+                Operation::ExtractFiles(lines) => {
+                    let files = {
+                        let doc = self.document.read().await;
+                        doc.select_files(&lines)
+                    };
+                    if files.is_empty() {
+                        info!("no file selected in lines: {:?}", lines);
+                        continue;
+                    }
+                    if let Err(e) = cmd_sender.send(Cmd::ExtractFiles(files)).await {
+                        error!("send cmd error: {}", e);
+                        return Err(ErrorKind::BrokenPipe.into());
+                    }
+                }
+                Operation::Add(paths, pwd) => {
+                    let paths = paths
+                        .into_iter()
+                        .map(|p| p.trim().to_string())
+                        .filter(|p| !p.is_empty())
+                        .collect::<Vec<_>>();
+                    if paths.is_empty() {
+                        info!("no path to add");
+                        continue;
+                    }
+                    // remembered like a typed one, the encrypted archive is listed with it
+                    if let Some(pwd) = pwd.filter(|p| !p.is_empty()) {
+                        self.remember_password(&pwd).await;
+                    }
+                    if let Err(e) = cmd_sender.send(Cmd::Add(paths)).await {
+                        error!("send cmd error: {}", e);
+                        return Err(ErrorKind::BrokenPipe.into());
+                    }
+                }
+                Operation::Delete(lines) => {
+                    let files = {
+                        let doc = self.document.read().await;
+                        doc.select_files(&lines)
+                    };
+                    if files.is_empty() {
+                        info!("no file selected in lines: {:?}", lines);
+                        continue;
+                    }
+                    if let Err(e) = cmd_sender.send(Cmd::Delete(files)).await {
+                        error!("send cmd error: {}", e);
+                        return Err(ErrorKind::BrokenPipe.into());
+                    }
+                }
+                Operation::RenameTarget(row, line, reply) => {
+                    let target = self.document.read().await.rename_target(row, &line);
+                    let _ = reply.send(target);
+                }
+                Operation::Rename { old, new } => {
+                    let new = new.trim().to_string();
+                    let taken = self.document.read().await.files().contains(&new);
+                    let refused = if new.is_empty() {
+                        Some("rename failed, the new name is empty".to_string())
+                    } else if taken {
+                        Some(format!("rename failed, {} exists in archive", new))
+                    } else {
+                        None
+                    };
+                    if let Some(status) = refused {
+                        self.push_status(&status).await;
+                        self.render().await?;
+                        continue;
+                    }
+                    if let Err(e) = cmd_sender.send(Cmd::Rename(old, new)).await {
+                        error!("send cmd error: {}", e);
+                        return Err(ErrorKind::BrokenPipe.into());
+                    }
+                }
+                Operation::Render => {
+                    self.render().await?;
+                }
+                Operation::FileDetail(line) => {
+                    self.file_detail(line).await;
+                }
+                Operation::Sort(key) => {
+                    let lines = {
+                        let mut doc = self.document.write().await;
+                        doc.sort(key);
+                        doc.output()
+                    };
+                    if let Err(e) = self.doc_sender.send(Pushment::Full(lines, None)).await {
+                        info!("pushment sender error: {}", e);
+                        return Err(ErrorKind::Interrupted.into());
+                    }
+                }
+                Operation::Filter(pattern) => {
+                    let lines = {
+                        let mut doc = self.document.write().await;
+                        doc.filter(&pattern);
+                        doc.output()
+                    };
+                    if let Err(e) = self.doc_sender.send(Pushment::Full(lines, None)).await {
+                        info!("pushment sender error: {}", e);
+                        return Err(ErrorKind::Interrupted.into());
+                    }
+                }
+                Operation::ToggleHidden => {
+                    let hidden = self.document.write().await.toggle_hidden();
+                    self.render().await?;
+                    let status = if hidden {
+                        "idle, hidden files skipped"
+                    } else {
+                        "idle, hidden files shown"
+                    };
+                    self.push_status(status).await;
+                }
+                Operation::FindFile(query, from) => {
+                    let (lines, row) = {
+                        let doc = self.document.read().await;
+                        (doc.output(), doc.find_file(&query, from))
+                    };
+                    let Some(row) = row else {
+                        info!("no file matches: {}", query);
+                        continue;
+                    };
+                    if let Err(e) = self
+                        .doc_sender
+                        .send(Pushment::Full(lines, Some((row, 0))))
+                        .await
+                    {
+                        info!("pushment sender error: {}", e);
+                        return Err(ErrorKind::Interrupted.into());
+                    }
+                }
+                Operation::PreviewExtract => {
+                    let extract_to_path = {
+                        let extract_to_path = self.extract_to_path.read().await;
+                        extract_to_path.to_str().unwrap().to_string()
+                    };
+                    let lines = {
+                        let mut doc = self.document.write().await;
+                        doc.layout_preview();
+                        doc.input(
+                            format!(
+                                "Set extract_path: {}/",
+                                extract_to_path.trim_end_matches('/')
+                            )
+                            .as_str(),
+                        );
+                        let conflicts = doc.conflicts(Path::new(&extract_to_path));
+                        if !conflicts.is_empty() {
+                            doc.input(&conflicts_line(&conflicts));
+                        }
+                        doc.output()
+                    };
+                    if let Err(e) = self.doc_sender.send(Pushment::Full(lines, None)).await {
+                        info!("pushment sender error: {}", e);
+                        return Err(ErrorKind::Interrupted.into());
+                    }
+                }
+                Operation::TechnicalList => {
+                    let technical_list = {
+                        let mut technical_list = self.technical_list.write().await;
+                        *technical_list = !*technical_list;
+                        *technical_list
+                    };
+                    let cmd = if technical_list {
+                        Cmd::TechnicalList
+                    } else {
+                        Cmd::List
+                    };
+                    if let Err(e) = cmd_sender.send(cmd).await {
+                        error!("send cmd error: {}", e);
+                        return Err(ErrorKind::BrokenPipe.into());
+                    }
+                }
+                Operation::ToggleFlatten => {
+                    let flatten = {
+                        let mut flatten = self.flatten.write().await;
+                        *flatten = !*flatten;
+                        *flatten
+                    };
+                    let lines = {
+                        let mut doc = self.document.write().await;
+                        doc.input(if flatten {
+                            "Set flatten: on"
+                        } else {
+                            "Set flatten: off"
+                        });
+                        doc.output()
+                    };
+                    if let Err(e) = self.doc_sender.send(Pushment::Full(lines, None)).await {
+                        info!("pushment sender error: {}", e);
+                        return Err(ErrorKind::Interrupted.into());
+                    }
+                }
+                Operation::ShowVolumes => {
+                    let found = {
+                        let mut doc = self.document.write().await;
+                        let volumes = if doc.has_volumes() {
+                            vec![]
+                        } else {
+                            find_volumes(Path::new(&*self.file.read().await))
+                        };
+                        let found = !volumes.is_empty();
+                        doc.set_volumes(volumes);
+                        found
+                    };
+                    if !found {
+                        self.push_status("idle, no volumes shown").await;
+                    }
+                    self.render().await?;
+                }
+                Operation::ToggleWrapDir => {
+                    let stem = archive_stem(&self.file.read().await);
+                    let lines = {
+                        // same lock order as the wrap after list: document, path, wrap
+                        let mut doc = self.document.write().await;
+                        let mut extract_to_path = self.extract_to_path.write().await;
+                        let mut wrap_dir = self.wrap_dir.write().await;
+                        if *wrap_dir {
+                            if extract_to_path.file_name().is_some_and(|n| *n == *stem) {
+                                extract_to_path.pop();
+                            }
+                        } else {
+                            extract_to_path.push(&stem);
+                        }
+                        *wrap_dir = !*wrap_dir;
+                        doc.input(&format!(
+                            "Extract to: {}",
+                            extract_to_path.to_str().unwrap()
+                        ));
+                        doc.input(&wrap_dir_input(*wrap_dir, &stem));
+                        doc.output()
+                    };
+                    if let Err(e) = self.doc_sender.send(Pushment::Full(lines, None)).await {
+                        info!("pushment sender error: {}", e);
+                        return Err(ErrorKind::Interrupted.into());
+                    }
+                }
+                Operation::SetOverwriteMode(mode) => {
+                    *self.overwrite.write().await = mode;
+                    let lines = {
+                        let mut doc = self.document.write().await;
+                        doc.set_overwrite(mode);
+                        doc.output()
+                    };
+                    if let Err(e) = self.doc_sender.send(Pushment::Full(lines, None)).await {
+                        info!("pushment sender error: {}", e);
+                        return Err(ErrorKind::Interrupted.into());
+                    }
+                }
+                Operation::OpenDestination => {
+                    if !self.execute_status.read().await.extracted() {
+                        info!("nothing extracted yet, do not open destination");
+                        continue;
+                    }
+                    let extract_to_path = self.extract_to_path.read().await.clone();
+                    open_destination(&self.opener, &extract_to_path);
+                }
+                Operation::ChecksumExtracted(kind) => {
+                    if !self.execute_status.read().await.extracted() {
+                        info!("nothing extracted yet, no checksum");
+                        continue;
+                    }
+                    let target = self.extract_to_path.read().await.clone();
+                    let files = self.document.read().await.extracted(&target);
+                    let count = files.len();
+                    self.push_status(&format!("{} of {} files ...", kind.label(), count))
+                        .await;
+                    // large files take a while, keep it off the runtime threads
+                    let rows =
+                        tokio::task::spawn_blocking(move || checksum_rows(&target, &files, kind))
+                            .await
+                            .unwrap_or_else(|e| {
+                                error!("checksum task error: {}", e);
+                                vec![]
+                            });
+                    {
+                        let mut doc = self.document.write().await;
+                        doc.input(&format!("Checksums: {}", kind.label()));
+                        for row in rows {
+                            doc.input(&format!("Checksum: {}", row));
+                        }
+                    }
+                    self.render().await?;
+                    self.push_status(&format!("idle, {} of {} files", kind.label(), count))
+                        .await;
+                }
+                Operation::ClearHistory => {
+                    if let Err(e) = std::fs::write(&self.password_history_file, "") {
+                        error!("clear password history error: {}", e);
+                    }
+                    let lines = {
+                        let mut doc = self.document.write().await;
+                        doc.input("Clear password history");
+                        doc.output()
+                    };
+                    if let Err(e) = self.doc_sender.send(Pushment::Full(lines, None)).await {
+                        info!("pushment sender error: {}", e);
+                        return Err(ErrorKind::Interrupted.into());
+                    }
+                }
+                Operation::FilePath(line, reply) => {
+                    let path = self.document.read().await.file_path(&line);
+                    let _ = reply.send(path);
+                }
+                oper @ (Operation::ExportJson(_) | Operation::ExportList(..)) => {
+                    // the rest of the operations go on, e.g. the password 7z may wait for
+                    {
+                        let mut pending = self.pending_exports.write().await;
+                        if matches!(*self.execute_status.read().await, ExecuteStatus::Pedding) {
+                            info!("export after the running command finished");
+                            pending.push(oper);
+                            continue;
+                        }
+                    }
+                    self.export(oper).await?;
+                }
+                Operation::Quit => {
+                    // 7z may wait for a password, close stdin so it gives up
+                    self.stdin_pipe.write().await.take();
+                    self.wait_finished().await;
+                    self.run_pending_exports().await?;
+                    info!("quit after command finished");
+                    // nvim quits, then doc channel closed, everything will be shutdown
+                    if let Err(e) = self.doc_sender.send(Pushment::None).await {
+                        info!("pushment sender error: {}", e);
+                        return Err(ErrorKind::Interrupted.into());
+                    }
+                }
+                Operation::Test => {
+                    if let Err(e) = cmd_sender.send(Cmd::Test).await {
+                        error!("send cmd error: {}", e);
+                        return Err(ErrorKind::BrokenPipe.into());
+                    }
+                }
+                Operation::ShowFormats => {
+                    if let Err(e) = cmd_sender.send(Cmd::Info).await {
                         error!("send cmd error: {}", e);
                         return Err(ErrorKind::BrokenPipe.into());
                     }
                 }
                 Operation::Retry => {
+                    // the previous command may still running, 'list' can only begin after it
+                    kill_running(&self.running).await;
                     {
                         let mut password = self.password.write().await;
                         password.take();
                     }
                     let _ = cmd_sender.try_send(Cmd::List);
                 }
+                Operation::Refresh => {
+                    kill_running(&self.running).await;
+                    // asked for a fresh look, do not replay the cached list
+                    self.list_cache.write().await.take();
+                    let _ = cmd_sender.try_send(Cmd::List);
+                }
+                Operation::EnterArchive(line) => {
+                    let member = self.document.read().await.file_path(&line);
+                    match member {
+                        Some(member) if is_archive_name(&member) => {
+                            if let Err(e) = cmd_sender.try_send(Cmd::Enter(member)) {
+                                info!("enter archive error: {}", e);
+                            }
+                        }
+                        Some(member) => {
+                            let status = format!("idle, {} is not an archive", member);
+                            self.push_status(&status).await;
+                        }
+                        None => info!("not a file row, nothing to enter"),
+                    }
+                }
+                Operation::LeaveArchive => {
+                    let Some(context) = self.archives.write().await.pop() else {
+                        self.push_status("idle, not inside a nested archive").await;
+                        continue;
+                    };
+                    // the nested one may still be listed, its files are about to go
+                    kill_running(&self.running).await;
+                    self.restore_archive(context).await;
+                    let _ = cmd_sender.try_send(Cmd::List);
+                }
+                Operation::Cancel => {
+                    if !matches!(*self.execute_status.read().await, ExecuteStatus::Pedding) {
+                        info!("nothing running, nothing to cancel");
+                        continue;
+                    }
+                    *self.cancelled.write().await = true;
+                    // 7z may wait for a password, close stdin so it can not hang on it
+                    self.stdin_pipe.write().await.take();
+                    kill_running(&self.running).await;
+                }
                 Operation::ExtractTo(path) => {
                     self.set_extract_to_path(&path).await;
                 }
                 Operation::Password(pwd) => {
+                    // the masked line is sent back when leaving insert mode again,
+                    // a typed password of stars differs from it unless it is the same one
+                    if self.masked_password.read().await.as_deref() == Some(pwd.as_str()) {
+                        info!("ignore masked password");
+                        continue;
+                    }
                     self.write_password(&pwd).await;
+                    let relist = {
+                        let status = self.execute_status.read().await;
+                        status.relist_with_password()
+                    };
+                    if relist {
+                        info!("list again with new password");
+                        let _ = cmd_sender.send(Cmd::List).await;
+                    }
                 }
-                Operation::SelectPassword(pwd) => {
-                    let should_retry = {
-                        // info!("check execute status start");
+                Operation::SelectPassword(choice) => {
+                    let Some(pwd) = history_password(&self.password_history_file, &choice) else {
+                        warn!("no password history entry for {:?}", choice);
+                        continue;
+                    };
+                    let write_stdin = {
                         let status = self.execute_status.read().await;
-                        // info!("recv password current status: {:?}", status);
-                        !matches!(*status, ExecuteStatus::Pedding)
+                        let stdin_pipe = self.stdin_pipe.read().await;
+                        status.write_selected_password(stdin_pipe.is_some())
                     };
-                    if should_retry {
-                        {
-                            let mut password = self.password.write().await;
-                            password.take();
-                        }
+                    // the command may finish right after the check, then the pipe is gone
+                    if write_stdin && self.write_password(&pwd).await {
+                        continue;
+                    }
+                    // queue it before listing, the prompt may come before this arm goes on
+                    self.selected_password.write().await.replace(pwd);
+                    // a running command that prompts later takes the queued one,
+                    // check again after queueing, it may have finished in between
+                    if !matches!(*self.execute_status.read().await, ExecuteStatus::Pedding) {
+                        self.password.write().await.take();
                         let _ = cmd_sender.send(Cmd::List).await;
-                        {
-                            let mut selected_password = self.selected_password.write().await;
-                            selected_password.replace(pwd);
-                        }
-                    } else {
-                        self.write_password(&pwd).await;
                     }
                 }
             }
@@ -187,75 +1003,271 @@ impl Z7 {
         Ok(())
     }
 
+    /// properties of the file on the line into the detail pane, "l -slt" runs aside
+    /// the command queue so the document keeps its layout, results are cached
+    async fn file_detail(&self, line: String) {
+        let seq = {
+            let mut seq = self.detail_seq.write().await;
+            *seq += 1;
+            *seq
+        };
+        let Some(path) = self.document.read().await.file_path(&line) else {
+            // not a file row, clear the pane
+            self.push_detail(seq, vec![]).await;
+            return;
+        };
+        let cached = self.details.read().await.get(&path).cloned();
+        if let Some(lines) = cached {
+            self.push_detail(seq, lines).await;
+            return;
+        }
+        let z7 = self.clone();
+        tokio::spawn(async move {
+            let (lines, ok) = z7.technical_detail(&path).await;
+            if ok {
+                z7.details.write().await.insert(path, lines.clone());
+            }
+            z7.push_detail(seq, lines).await;
+        });
+    }
+
+    /// "l -slt" of one path, true if 7z succeeded, a failure is not cached
+    async fn technical_detail(&self, path: &str) -> (Vec<String>, bool) {
+        let name = path.trim_end_matches('/');
+        let mut args = vec!["l".to_string(), "-slt".to_string()];
+        args.extend(self.extra_args(&Cmd::TechnicalList));
+        args.push(self.file.read().await.clone());
+        if let Some(pwd) = self.password.read().await.clone() {
+            args.push(format!("-p{}", pwd));
+        }
+        args.extend(["--".to_string(), name.to_string()]);
+        // no stdin, a password prompt fails right away instead of waiting
+        let output = Command::new(&self.seven_zip_bin)
+            .args(&args)
+            .stdin(Stdio::null())
+            .kill_on_drop(true)
+            .output()
+            .await;
+        match output {
+            Ok(output) => (
+                detail_lines(&String::from_utf8_lossy(&output.stdout), name),
+                output.status.success(),
+            ),
+            Err(e) => (vec![spawn_error_line(&self.seven_zip_bin, &e)], false),
+        }
+    }
+
+    async fn push_detail(&self, seq: usize, lines: Vec<String>) {
+        if *self.detail_seq.read().await != seq {
+            return;
+        }
+        if let Err(e) = self.doc_sender.send(Pushment::Detail(lines)).await {
+            info!("pushment sender error: {}", e);
+        }
+    }
+
+    /// push the whole document, the buffer is replaced by it
+    async fn render(&self) -> tokio::io::Result<()> {
+        let lines = self.document.read().await.output();
+        if let Err(e) = self.doc_sender.send(Pushment::Full(lines, None)).await {
+            info!("pushment sender error: {}", e);
+            return Err(ErrorKind::Interrupted.into());
+        }
+        Ok(())
+    }
+
     async fn set_extract_to_path(&mut self, path: &str) {
         let mut extract_to_path = self.extract_to_path.write().await;
-        *extract_to_path = PathBuf::from(path);
+        let path = resolve_path(path, &self.archive_dir);
+        // leaving insert mode sends the line back unchanged, keep the wrap then
+        let user_typed = *extract_to_path != path;
+        *extract_to_path = path;
+        if let Some(file) = &self.last_extract_path_file {
+            if let Err(e) = std::fs::write(file, extract_to_path.to_str().unwrap()) {
+                error!("save last extract path error: {}", e);
+            }
+        }
         let input = format!("Extract to: {}", extract_to_path.to_str().unwrap());
         let mut doc = self.document.write().await;
         doc.input(&input);
+        if user_typed {
+            *self.wrap_dir.write().await = false;
+            doc.input(&wrap_dir_input(false, ""));
+        }
     }
     /// write password to child stdin,
-    /// then child will continue to execute with output
-    async fn write_password(&mut self, pwd: &str) {
-        let mut stdin = self.stdin_pipe.write().await;
-        // will set stdin to None
-        if let Some(mut pipe) = stdin.take() {
-            info!("writed password: {}", pwd);
-            pipe.write_all(pwd.as_bytes())
-                .await
-                .expect("write password error");
+    /// then child will continue to execute with output,
+    /// false if there is no stdin pipe to write
+    async fn write_password(&mut self, pwd: &str) -> bool {
+        let display = if self.mask_password {
+            mask(pwd)
         } else {
-            info!("7z command stdin pipe is none");
-        }
-        {
-            let mut password = self.password.write().await;
-            let new_password = pwd.to_string();
+            pwd.to_string()
+        };
+        let written = {
+            let mut stdin = self.stdin_pipe.write().await;
+            // will set stdin to None
+            if let Some(mut pipe) = stdin.take() {
+                info!("writed password: {}", display);
+                pipe.write_all(pwd.as_bytes())
+                    .await
+                    .expect("write password error");
+                true
+            } else {
+                info!("7z command stdin pipe is none");
+                false
+            }
+        };
+        self.remember_password(pwd).await;
+        written
+    }
+
+    /// keep the password for the next commands and show it on the password line
+    async fn remember_password(&mut self, pwd: &str) {
+        {
+            let mut password = self.password.write().await;
+            let new_password = pwd.to_string();
             if password.is_some() && password.as_ref().unwrap() == &new_password {
                 return;
             }
             password.replace(new_password);
         }
-        {
-            let mut doc = self.document.write().await;
-            doc.input(format!("Input password: {}", pwd).as_str());
-        }
+        let display = if self.mask_password {
+            let masked = mask(pwd);
+            self.masked_password.write().await.replace(masked.clone());
+            masked
+        } else {
+            pwd.to_string()
+        };
+        let mut doc = self.document.write().await;
+        doc.input(format!("Input password: {}", display).as_str());
     }
 
-    /// allways receive commands from cmd_recv
+    /// allways receive commands from cmd_recv,
+    /// cmd_sender is used to list again after the archive has been changed
     async fn executing_cmd(
         &mut self,
         mut cmd_recv: mpsc::Receiver<Cmd>,
+        cmd_sender: mpsc::Sender<Cmd>,
         opt_sender: mpsc::Sender<Option<(Vec<u8>, usize)>>,
     ) -> tokio::io::Result<()> {
         while let Some(cmd) = cmd_recv.recv().await {
             info!("recv cmd : {:?}", cmd);
             let opt_sender = opt_sender.clone();
             let stdin_pipe = self.stdin_pipe.clone();
+            let running = self.running.clone();
+            let file = self.file.read().await.clone();
             {
                 let mut status = self.execute_status.write().await;
                 *status = ExecuteStatus::Pedding;
             }
+            let started = Instant::now();
+            self.push_status(format!("running {}", cmd.name()).as_str())
+                .await;
             let password = {
                 let password = self.password.read().await;
                 password.clone()
             };
-            let (exit_status, cmd) = match cmd {
+            {
+                // leave the preview, file list renders archive paths again
+                let mut doc = self.document.write().await;
+                doc.input("Set extract_path:");
+            }
+            // fail fast, 7z would only complain after opening the archive
+            if matches!(cmd, Cmd::Extract | Cmd::ExtractFiles(_) | Cmd::ExtractTar) {
+                let extract_to_path = self.extract_to_path.read().await.clone();
+                if let Err(e) = check_extract_path(&extract_to_path) {
+                    self.document.write().await.layout_extract();
+                    let line = format!("ERROR: {}", e);
+                    self.report_error(&line, &format!("{} failed, {}", cmd.name(), e))
+                        .await;
+                    continue;
+                }
+            }
+            let extra_args = self.extra_args(&cmd);
+            let ctx = ExecuteContext {
+                bin: &self.seven_zip_bin,
+                extra_args: &extra_args,
+                opt_sender,
+                stdin_pipe,
+                running,
+            };
+            // the dir a nested archive was extracted to, dropped with it if entering failed
+            let mut entered: Option<TempDir> = None;
+            let (result, cmd) = match cmd {
                 Cmd::List => {
                     {
                         let mut doc = self.document.write().await;
                         doc.layout_list();
-                        doc.input(format!("Extract file: {}", self.file).as_str());
+                        doc.input(format!("Extract file: {}", file).as_str());
                         let extract_to_path = self.extract_to_path.read().await;
                         doc.input(
                             format!("Extract to: {}", extract_to_path.to_str().unwrap()).as_str(),
                         );
                     }
+                    let modified = modified_time(&file);
+                    let cached = match (&*self.list_cache.read().await, modified) {
+                        (Some(cache), Some(modified)) if cache.hit(&file, modified) => {
+                            Some(cache.lines.clone())
+                        }
+                        _ => None,
+                    };
+                    let result = match cached {
+                        Some(lines) => {
+                            info!("list from cache, {} lines", lines.len());
+                            replay_output(&lines, ctx.opt_sender).await
+                        }
+                        None => {
+                            let (opt_sender, recorded) = record_output(ctx.opt_sender.clone());
+                            let result =
+                                execute_list(ExecuteContext { opt_sender, ..ctx }, &file, password)
+                                    .await;
+                            let lines = recorded.await.unwrap_or_default();
+                            // header-encrypted archives are listed with a password, never cache them
+                            let no_password = self.password.read().await.is_none();
+                            if let (Ok(status), Some(modified)) = (&result, modified) {
+                                if status.success() && no_password {
+                                    self.list_cache.write().await.replace(ListCache {
+                                        file: file.clone(),
+                                        modified,
+                                        lines,
+                                    });
+                                }
+                            }
+                            result
+                        }
+                    };
+                    (result, Cmd::List)
+                }
+                Cmd::Extract => {
+                    {
+                        let mut doc = self.document.write().await;
+                        doc.layout_extract();
+                    }
+                    let extract_to_path = {
+                        let extract_to_path = self.extract_to_path.read().await;
+                        extract_to_path.to_str().unwrap().to_string()
+                    };
+                    let flatten = *self.flatten.read().await;
+                    let overwrite = *self.overwrite.read().await;
                     (
-                        execute_list(&self.file, opt_sender, stdin_pipe, password).await?,
-                        Cmd::List,
+                        execute_extract(ctx, &file, password, &extract_to_path, flatten, overwrite)
+                            .await,
+                        Cmd::Extract,
                     )
                 }
-                Cmd::Extract => {
+                Cmd::ExtractTar => {
+                    {
+                        let mut doc = self.document.write().await;
+                        doc.layout_extract();
+                    }
+                    (
+                        self.extract_tar(&extra_args, ctx.opt_sender, password)
+                            .await,
+                        Cmd::ExtractTar,
+                    )
+                }
+                Cmd::ExtractFiles(files) => {
                     {
                         let mut doc = self.document.write().await;
                         doc.layout_extract();
@@ -264,33 +1276,169 @@ impl Z7 {
                         let extract_to_path = self.extract_to_path.read().await;
                         extract_to_path.to_str().unwrap().to_string()
                     };
+                    let flatten = *self.flatten.read().await;
+                    let overwrite = *self.overwrite.read().await;
                     (
-                        execute_extract(
-                            &self.file,
-                            opt_sender,
-                            stdin_pipe,
+                        execute_extract_files(
+                            ctx,
+                            &file,
                             password,
                             &extract_to_path,
+                            flatten,
+                            overwrite,
+                            &files,
                         )
-                        .await?,
-                        Cmd::Extract,
+                        .await,
+                        Cmd::ExtractFiles(files),
+                    )
+                }
+                // 7z will create the archive if it does not exist yet
+                Cmd::Add(paths) => {
+                    // an empty password encrypts nothing
+                    let password = password.filter(|p| !p.is_empty());
+                    let encrypt_headers = password.is_some() && encrypt_headers(&file);
+                    {
+                        let mut doc = self.document.write().await;
+                        doc.layout_add();
+                        doc.input(format!("Add file: {}", file).as_str());
+                        doc.input(&encryption_input(password.is_some(), encrypt_headers));
+                    }
+                    (
+                        execute_add(ctx, &file, password, encrypt_headers, &paths).await,
+                        Cmd::Add(paths),
                     )
                 }
+                Cmd::Delete(files) => {
+                    {
+                        let mut doc = self.document.write().await;
+                        doc.layout_delete();
+                    }
+                    (
+                        execute_delete(ctx, &file, password, &files).await,
+                        Cmd::Delete(files),
+                    )
+                }
+                Cmd::Rename(old, new) => {
+                    {
+                        let mut doc = self.document.write().await;
+                        doc.layout_rename();
+                    }
+                    (
+                        execute_rename(ctx, &file, password, (&old, &new)).await,
+                        Cmd::Rename(old, new),
+                    )
+                }
+                Cmd::TechnicalList => {
+                    {
+                        let mut doc = self.document.write().await;
+                        doc.layout_technical_list();
+                    }
+                    (
+                        execute_technical_list(ctx, &file, password).await,
+                        Cmd::TechnicalList,
+                    )
+                }
+                Cmd::Test => {
+                    {
+                        let mut doc = self.document.write().await;
+                        doc.layout_test();
+                    }
+                    (execute_test(ctx, &file, password).await, Cmd::Test)
+                }
+                Cmd::Enter(member) => {
+                    {
+                        let mut doc = self.document.write().await;
+                        doc.layout_extract();
+                    }
+                    let result = match temp_dir("vui-7z-enter-") {
+                        Ok(dir) => {
+                            let result = execute_extract_files(
+                                ctx,
+                                &file,
+                                password,
+                                dir.path().to_str().unwrap(),
+                                true,
+                                OverwriteMode::Overwrite,
+                                std::slice::from_ref(&member),
+                            )
+                            .await;
+                            entered = Some(dir);
+                            result
+                        }
+                        Err(e) => Err(e),
+                    };
+                    (result, Cmd::Enter(member))
+                }
+                Cmd::Info => {
+                    {
+                        let mut doc = self.document.write().await;
+                        doc.layout_info();
+                    }
+                    (execute_info(ctx).await, Cmd::Info)
+                }
+            };
+            let peeked = match cmd {
+                Cmd::Extract | Cmd::ExtractFiles(_) | Cmd::ExtractTar => self.end_peek().await,
+                _ => None,
             };
+            let cancelled = std::mem::take(&mut *self.cancelled.write().await);
+            let exit_status = match result {
+                _ if cancelled => {
+                    self.report_cancelled(&cmd).await;
+                    continue;
+                }
+                Ok(exit_status) => exit_status,
+                Err(e) => {
+                    // the command never ran, report it and keep waiting for the next one
+                    let line = spawn_error_line(&self.seven_zip_bin, &e);
+                    self.report_error(&line, &format!("{} failed, {}", cmd.name(), e))
+                        .await;
+                    continue;
+                }
+            };
+            let mut wrong_password = None;
+            let mut status_message = if exit_status.success() {
+                "idle".to_string()
+            } else {
+                format!("{} failed, {}", cmd.name(), exit_status)
+            };
+            if let Some(dir) = peeked.filter(|_| exit_status.success()) {
+                open_destination(&self.opener, &dir);
+                status_message = format!("idle, peeked into {}", dir.display());
+            }
             {
                 let mut status = self.execute_status.write().await;
                 if exit_status.success() {
-                    *status = ExecuteStatus::None;
+                    *self.password_attempts.write().await = 0;
+                    *status = match &cmd {
+                        Cmd::Extract | Cmd::ExtractFiles(_) | Cmd::ExtractTar => {
+                            ExecuteStatus::Extract(exit_status)
+                        }
+                        _ => ExecuteStatus::None,
+                    };
                     let mut doc = self.document.write().await;
                     if let Some(pwd) = self.password.read().await.clone() {
                         doc.input(format!("Save password: {}", pwd).as_str());
                     }
+                    if let Some(pwd) = self.candidate.write().await.take() {
+                        let pwd = if self.mask_password { mask(&pwd) } else { pwd };
+                        info!("candidate password worked: {}", pwd);
+                        status_message = format!("idle, candidate password worked: {}", pwd);
+                    }
+                    // the list is refreshed after add or delete, wrap only once
+                    let wrapped = *self.wrap_dir.read().await;
                     match cmd {
-                        Cmd::List if check_same_directory(&doc.files()).is_none() => {
-                            let filename = PathBuf::from(&self.file);
-                            let filename = filename.file_stem().unwrap();
+                        // nothing would go into the subdirectory of an empty archive
+                        Cmd::List
+                            if !wrapped
+                                && !doc.is_empty_archive()
+                                && check_same_directory(&doc.files()).is_none() =>
+                        {
+                            let filename = archive_stem(&file);
                             let mut extract_to_path = self.extract_to_path.write().await;
-                            extract_to_path.push(filename);
+                            extract_to_path.push(&filename);
+                            *self.wrap_dir.write().await = true;
+                            doc.input(&wrap_dir_input(true, &filename));
                             let input =
                                 format!("Extract to: {}", extract_to_path.to_str().unwrap());
                             doc.input(&input);
@@ -299,21 +1447,344 @@ impl Z7 {
                                 .await
                                 .expect("send string line error");
                         }
+                        Cmd::Enter(ref member) => {
+                            if let Some(dir) = entered.take() {
+                                self.push_archive(&file, member, dir).await;
+                                if let Err(e) = cmd_sender.try_send(Cmd::List) {
+                                    info!("list nested archive error: {}", e);
+                                }
+                            }
+                        }
+                        Cmd::Add(_) | Cmd::Delete(_) | Cmd::Rename(_, _) => {
+                            // the mtime may stay in the same second, drop the cache anyway
+                            self.list_cache.write().await.take();
+                            // refresh the file list, the channel may be occupied by the next command
+                            if let Err(e) = cmd_sender.try_send(Cmd::List) {
+                                info!("list after archive changed error: {}", e);
+                            }
+                        }
                         _ => {}
                     }
                 } else {
-                    self.password.write().await.take();
+                    // "i" does not open the archive, the password is not to blame,
+                    // the outer archive was listed with it before entering
+                    if !matches!(cmd, Cmd::Info | Cmd::Enter(_)) {
+                        self.password.write().await.take();
+                    }
+                    if matches!(cmd, Cmd::List) {
+                        let mut candidate = self.candidate.write().await;
+                        if candidate.is_some() {
+                            // try the next one, until a list succeeds or nothing left
+                            *candidate = self.candidates.write().await.pop_front();
+                            match candidate.as_ref() {
+                                Some(next) => {
+                                    self.password.write().await.replace(next.clone());
+                                    status_message =
+                                        "list failed, trying the next candidate password"
+                                            .to_string();
+                                    if let Err(e) = cmd_sender.try_send(Cmd::List) {
+                                        info!("list with candidate password error: {}", e);
+                                    }
+                                }
+                                None => {
+                                    status_message =
+                                        "list failed, none of the candidate passwords worked"
+                                            .to_string();
+                                }
+                            }
+                        }
+                    }
+                    let mut doc = self.document.write().await;
+                    if matches!(
+                        cmd,
+                        Cmd::List | Cmd::Extract | Cmd::ExtractFiles(_) | Cmd::ExtractTar
+                    ) && doc.failure() == Some(Failure::WrongPassword)
+                    {
+                        let mut attempts = self.password_attempts.write().await;
+                        *attempts += 1;
+                        doc.input(
+                            format!(
+                                "Password attempts: {}/{}",
+                                attempts, self.max_password_attempts
+                            )
+                            .as_str(),
+                        );
+                        wrong_password = Some((doc.output(), doc.password_line()));
+                    }
                     *status = match cmd {
-                        Cmd::List => ExecuteStatus::List(exit_status),
-                        Cmd::Extract => ExecuteStatus::Extract(exit_status),
+                        Cmd::List | Cmd::TechnicalList => ExecuteStatus::List(exit_status),
+                        Cmd::Extract | Cmd::ExtractFiles(_) | Cmd::ExtractTar => {
+                            ExecuteStatus::Extract(exit_status)
+                        }
+                        Cmd::Test => ExecuteStatus::Test(exit_status),
+                        Cmd::Add(_) => ExecuteStatus::Add(exit_status),
+                        Cmd::Delete(_) => ExecuteStatus::Delete(exit_status),
+                        Cmd::Rename(_, _) => ExecuteStatus::Rename(exit_status),
+                        Cmd::Info => ExecuteStatus::Info(exit_status),
+                        Cmd::Enter(_) => ExecuteStatus::Extract(exit_status),
                     };
                 }
             }
+            // the hint has the attempt count now, keep the cursor on the password line
+            if let Some((lines, password_line)) = wrong_password {
+                if let Err(e) = self
+                    .doc_sender
+                    .send(Pushment::Full(lines, password_line.map(|l| (l, 1))))
+                    .await
+                {
+                    info!("pushment sender error: {}", e);
+                }
+            }
+            let status_message = format!(
+                "{}, took {}",
+                status_message,
+                elapsed_text(started.elapsed())
+            );
+            self.push_status(&status_message).await;
+            self.run_pending_exports().await?;
         }
         info!("cmd recv closed");
         Ok(())
     }
 
+    /// --7z-arg, then the charset switch for list and extract
+    fn extra_args(&self, cmd: &Cmd) -> Vec<String> {
+        let mut args = self.extra_args.clone();
+        if self.force_utf8
+            && matches!(
+                cmd,
+                Cmd::List
+                    | Cmd::TechnicalList
+                    | Cmd::Extract
+                    | Cmd::ExtractFiles(_)
+                    | Cmd::ExtractTar
+            )
+        {
+            args.push(FORCE_UTF8.to_string());
+        }
+        args
+    }
+
+    /// 7z only takes off the outer layer of .tar.gz, the tar is extracted by a second run,
+    /// an inner file that is not a tar is moved to the destination as it is
+    async fn extract_tar(
+        &self,
+        extra_args: &[String],
+        opt_sender: mpsc::Sender<Option<(Vec<u8>, usize)>>,
+        password: Option<String>,
+    ) -> tokio::io::Result<ExitStatus> {
+        // a fresh private dir for each extract, removed when the handle drops
+        let stage = tempfile::Builder::new().prefix("vui-7z-tar-").tempdir()?;
+        let stage_dir = stage.path();
+        let file = self.file.read().await.clone();
+        let extract_to_path = self.extract_to_path.read().await.clone();
+        let flatten = *self.flatten.read().await;
+        let overwrite = *self.overwrite.read().await;
+        let result = async {
+            self.push_status("running extract tar (1/2 decompress)")
+                .await;
+            let status = execute_extract(
+                ExecuteContext {
+                    bin: &self.seven_zip_bin,
+                    extra_args,
+                    opt_sender: opt_sender.clone(),
+                    stdin_pipe: self.stdin_pipe.clone(),
+                    running: self.running.clone(),
+                },
+                &file,
+                password,
+                stage_dir.to_str().unwrap(),
+                false,
+                OverwriteMode::Overwrite,
+            )
+            .await?;
+            if !status.success() {
+                return Ok(status);
+            }
+            let inner = std::fs::read_dir(stage_dir)?
+                .filter_map(|e| e.ok())
+                .map(|e| e.path())
+                .find(|p| p.is_file())
+                .ok_or_else(|| std::io::Error::new(ErrorKind::NotFound, "nothing decompressed"))?;
+            if !is_tar(&inner) {
+                info!("inner file is not a tar: {:?}", inner);
+                std::fs::create_dir_all(&extract_to_path)?;
+                let dest = extract_to_path.join(inner.file_name().unwrap());
+                // rename does not work across filesystems, the temp dir may be another one
+                if std::fs::rename(&inner, &dest).is_err() {
+                    std::fs::copy(&inner, &dest)?;
+                }
+                return Ok(status);
+            }
+            self.push_status("running extract tar (2/2 unpack)").await;
+            execute_extract(
+                ExecuteContext {
+                    bin: &self.seven_zip_bin,
+                    extra_args,
+                    opt_sender,
+                    stdin_pipe: self.stdin_pipe.clone(),
+                    running: self.running.clone(),
+                },
+                inner.to_str().unwrap(),
+                None,
+                extract_to_path.to_str().unwrap(),
+                flatten,
+                overwrite,
+            )
+            .await
+        }
+        .await;
+        if let Err(e) = stage.close() {
+            error!("remove stage dir error: {}", e);
+        }
+        result
+    }
+
+    /// --json and --export-list, or <space>E
+    async fn export(&self, oper: Operation) -> tokio::io::Result<()> {
+        match oper {
+            Operation::ExportJson(path) => {
+                let json = self.document.read().await.to_json();
+                if let Err(e) = export_text(&path, &format!("{}\n", json)) {
+                    error!("export json to {} error: {}", path.display(), e);
+                }
+            }
+            Operation::ExportList(path, destinations) => {
+                let list = {
+                    let doc = self.document.read().await;
+                    let target = self.extract_to_path.read().await;
+                    doc.export_list(destinations.then_some(target.as_path()))
+                };
+                match export_text(&path, &list) {
+                    Ok(()) => {
+                        let status = format!(
+                            "idle, exported {} names to {}",
+                            list.lines().count(),
+                            path.display()
+                        );
+                        self.push_status(&status).await;
+                    }
+                    Err(e) => {
+                        error!("export list to {} error: {}", path.display(), e);
+                        self.document.write().await.input(&format!(
+                            "ERROR: can not export the list to {}: {}",
+                            path.display(),
+                            e
+                        ));
+                        self.render().await?;
+                        self.push_status("export list failed").await;
+                    }
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// the exports queued while the command was running, the lock is kept
+    /// until they are written, so a quit waits for them
+    async fn run_pending_exports(&self) -> tokio::io::Result<()> {
+        let mut pending = self.pending_exports.write().await;
+        for oper in std::mem::take(&mut *pending) {
+            self.export(oper).await?;
+        }
+        Ok(())
+    }
+
+    /// until the running command finished
+    async fn wait_finished(&self) {
+        loop {
+            {
+                let status = self.execute_status.read().await;
+                if !matches!(*status, ExecuteStatus::Pedding) {
+                    break;
+                }
+            }
+            sleep(Duration::from_millis(50)).await;
+        }
+    }
+
+    /// killed by Cancel, back to idle, the files found in the destination may be partial
+    async fn report_cancelled(&self, cmd: &Cmd) {
+        info!("{} cancelled", cmd.name());
+        *self.execute_status.write().await = ExecuteStatus::None;
+        let partial = if matches!(cmd, Cmd::Extract | Cmd::ExtractFiles(_) | Cmd::ExtractTar) {
+            let target = self.extract_to_path.read().await.clone();
+            self.document
+                .read()
+                .await
+                .extracted(&target)
+                .into_iter()
+                .map(|(_, dest)| dest.display().to_string())
+                .collect()
+        } else {
+            vec![]
+        };
+        self.document.write().await.cancelled(cmd.name(), partial);
+        if let Err(e) = self.render().await {
+            info!("render cancelled error: {}", e);
+        }
+        self.push_status(&format!("idle, {} cancelled", cmd.name()))
+            .await;
+        if let Err(e) = self.run_pending_exports().await {
+            error!("export after cancelled error: {}", e);
+        }
+    }
+
+    /// nothing was read from 7z, so write the error line into the document ourselves
+    async fn report_error(&self, line: &str, status: &str) {
+        {
+            let mut status = self.execute_status.write().await;
+            *status = ExecuteStatus::None;
+        }
+        let lines = {
+            let mut doc = self.document.write().await;
+            doc.input(line);
+            doc.output()
+        };
+        if let Err(e) = self.doc_sender.send(Pushment::Full(lines, None)).await {
+            info!("pushment sender error: {}", e);
+        }
+        self.push_status(status).await;
+        if let Err(e) = self.run_pending_exports().await {
+            error!("export after error: {}", e);
+        }
+    }
+
+    /// update the status and footer lines of document, then push them to nvim
+    async fn push_status(&self, status: &str) {
+        let context = self.context(status).await;
+        let (line, footer) = {
+            let mut doc = self.document.write().await;
+            doc.input(format!("Status: {}", status).as_str());
+            doc.input(format!("Set context: {}", context).as_str());
+            (doc.status(), doc.footer())
+        };
+        for pushment in [
+            Pushment::Line(STATUS_LINE, line),
+            Pushment::Line(FOOTER_LINE, footer),
+        ] {
+            if let Err(e) = self.doc_sender.send(pushment).await {
+                info!("pushment sender error: {}", e);
+            }
+        }
+    }
+
+    /// what the footer hints for, a wrong password is the one to fix first
+    async fn context(&self, status: &str) -> &'static str {
+        if status.starts_with("running") {
+            return "running";
+        }
+        let wrong_password = self.document.read().await.failure() == Some(Failure::WrongPassword);
+        match *self.execute_status.read().await {
+            ExecuteStatus::Pedding => "running",
+            _ if wrong_password => "password",
+            ExecuteStatus::Extract(status) if status.success() => "extracted",
+            _ if status.contains("failed") => "failed",
+            _ => "list",
+        }
+    }
+
     /// allways receive output from commands by opt_recv
     /// then push document to nvim through doc_sender
     async fn read_document(
@@ -321,26 +1792,117 @@ impl Z7 {
         mut opt_recv: mpsc::Receiver<Option<(Vec<u8>, usize)>>,
         oper_sender: mpsc::Sender<Operation>,
     ) -> tokio::io::Result<()> {
+        let mut last_status = String::new();
+        // the progress row is on the buffer, then only that row is pushed
+        let mut last_progress: Option<String> = None;
+        // file rows have been pushed by lines, None means a full push is needed
+        let mut pushed_rows: Option<usize> = None;
         while let Some(line) = opt_recv.recv().await {
             match line {
                 Some((line, fd)) => {
-                    let line = from_utf8(&line).expect("can not convert to utf8");
+                    // bytes of a whole line, invalid sequences are replaced rather than panic
+                    let line = String::from_utf8_lossy(&line);
+                    let line = line.as_ref();
                     info!("recv output: {},{}", fd, line);
-                    {
+                    let status = {
                         let mut doc = self.document.write().await;
                         doc.input(line);
+                        doc.status()
+                    };
+                    // the file list is growing, push the new rows only
+                    let appending = {
+                        let doc = self.document.read().await;
+                        doc.appending_files(pushed_rows.unwrap_or(0))
+                    };
+                    match (pushed_rows, appending) {
+                        (Some(from), Some((first_line, rows))) => {
+                            pushed_rows = Some(from + rows.len());
+                            for (i, row) in rows.into_iter().enumerate() {
+                                if let Err(e) = self
+                                    .doc_sender
+                                    .send(Pushment::Line(first_line + i as u64, row))
+                                    .await
+                                {
+                                    info!("pushment sender error: {}", e);
+                                    return Err(ErrorKind::Interrupted.into());
+                                }
+                            }
+                        }
+                        // layout above the rows is not on the buffer yet
+                        (None, Some((_, rows))) => {
+                            pushed_rows = Some(rows.len());
+                            let lines = {
+                                let doc = self.document.read().await;
+                                doc.output()
+                            };
+                            if let Err(e) = self.doc_sender.send(Pushment::Full(lines, None)).await
+                            {
+                                info!("pushment sender error: {}", e);
+                                return Err(ErrorKind::Interrupted.into());
+                            }
+                        }
+                        (_, None) => pushed_rows = None,
+                    }
+                    // the extract layout is not on the buffer until the first frame
+                    let progress = {
+                        let doc = self.document.read().await;
+                        doc.progress()
+                    };
+                    match (&last_progress, progress) {
+                        (None, Some((_, content))) => {
+                            last_progress = Some(content);
+                            let lines = {
+                                let doc = self.document.read().await;
+                                doc.output()
+                            };
+                            if let Err(e) = self.doc_sender.send(Pushment::Full(lines, None)).await
+                            {
+                                info!("pushment sender error: {}", e);
+                                return Err(ErrorKind::Interrupted.into());
+                            }
+                        }
+                        (Some(last), Some((line, content))) if *last != content => {
+                            last_progress = Some(content.clone());
+                            if let Err(e) =
+                                self.doc_sender.send(Pushment::Line(line, content)).await
+                            {
+                                info!("pushment sender error: {}", e);
+                                return Err(ErrorKind::Interrupted.into());
+                            }
+                        }
+                        (_, None) => last_progress = None,
+                        _ => {}
+                    }
+                    // only the spinner moved, do not rebuild the whole buffer
+                    if status != last_status {
+                        last_status = status.clone();
+                        if let Err(e) = self
+                            .doc_sender
+                            .send(Pushment::Line(STATUS_LINE, status))
+                            .await
+                        {
+                            info!("pushment sender error: {}", e);
+                            return Err(ErrorKind::Interrupted.into());
+                        }
                     }
                     if line.starts_with("Enter password") {
                         {
                             let mut doc = self.document.write().await;
+                            doc.input("Set context: password");
+                            if self.mask_password {
+                                doc.input("Mask password history");
+                            }
+                            doc.input(
+                                format!("Password history limit: {}", self.history_limit).as_str(),
+                            );
                             doc.input(
                                 format!("Password history file: {}", self.password_history_file)
                                     .as_str(),
                             );
                         }
-                        let lines = {
+                        let (lines, password_line) = {
                             let doc = self.document.read().await;
-                            doc.output()
+                            (doc.output(), doc.password_line())
                         };
                         let selected_password = {
                             let mut selected_password = self.selected_password.write().await;
@@ -350,7 +1912,7 @@ impl Z7 {
                             .doc_sender
                             .send(Pushment::Full(lines, {
                                 if selected_password.is_none() {
-                                    Some((PASSWORD_LINE, 1))
+                                    password_line.map(|l| (l, 1))
                                 } else {
                                     None
                                 }
@@ -370,128 +1932,809 @@ impl Z7 {
                 }
                 // "None" means a command is finished, but we still wait for other commands output
                 None => {
-                    let lines = {
+                    pushed_rows = None;
+                    last_progress = None;
+                    let (lines, failure, password_line) = {
                         let doc = self.document.read().await;
-                        doc.output()
+                        (doc.output(), doc.failure(), doc.password_line())
                     };
-                    if let Err(e) = self.doc_sender.send(Pushment::Full(lines, None)).await {
+                    // a wrong password can be retried, put the cursor on the password line
+                    let cursor = match failure {
+                        Some(Failure::WrongPassword) => password_line.map(|l| (l, 1)),
+                        _ => None,
+                    };
+                    if let Err(e) = self.doc_sender.send(Pushment::Full(lines, cursor)).await {
                         info!("pushment sender error: {}", e);
                         return Err(ErrorKind::Interrupted.into());
                     }
                 }
             }
         }
-        // do not care occur error
-        info!("output: finished");
-        Ok(())
+        // do not care occur error
+        info!("output: finished");
+        Ok(())
+    }
+}
+
+/// "~", "~user", "$VAR" and "${VAR}" of a path typed by the user,
+/// unknown users and unset variables are kept as they are
+pub fn expand_path(path: &str) -> String {
+    let path = match path.strip_prefix('~') {
+        Some(rest) => {
+            let (user, rest) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+            let home = if user.is_empty() {
+                std::env::var("HOME").ok()
+            } else {
+                user_home(user)
+            };
+            match home {
+                Some(home) => format!("{}{}", home, rest),
+                None => path.to_string(),
+            }
+        }
+        None => path.to_string(),
+    };
+    let mut expanded = String::new();
+    let mut rest = path.as_str();
+    while let Some(i) = rest.find('$') {
+        expanded.push_str(&rest[..i]);
+        let after = &rest[i + 1..];
+        let (name, len) = match after.strip_prefix('{') {
+            Some(braced) => match braced.find('}') {
+                Some(end) => (&braced[..end], end + 2),
+                None => ("", 0),
+            },
+            None => {
+                let end = after
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .unwrap_or(after.len());
+                (&after[..end], end)
+            }
+        };
+        match std::env::var(name) {
+            Ok(value) if !name.is_empty() => expanded.push_str(&value),
+            _ => expanded.push_str(&rest[i..i + 1 + len]),
+        }
+        rest = &after[len..];
+    }
+    expanded.push_str(rest);
+    expanded
+}
+
+/// home directory of a user from /etc/passwd
+fn user_home(user: &str) -> Option<String> {
+    let passwd = std::fs::read_to_string("/etc/passwd").ok()?;
+    passwd.lines().find_map(|line| {
+        let fields = line.split(':').collect::<Vec<_>>();
+        (fields.len() > 5 && fields[0] == user).then(|| fields[5].to_string())
+    })
+}
+
+/// expanded, then a relative path is taken from base, the directory of the archive
+pub fn resolve_path(path: &str, base: &Path) -> PathBuf {
+    let path = PathBuf::from(expand_path(path));
+    if path.is_relative() {
+        base.join(path)
+    } else {
+        path
+    }
+}
+
+/// entries of the directory part of a partial path,
+/// kept with the typed prefix so the completed text replaces it, folders end with '/'
+pub fn complete_path(partial: &str, base: &Path) -> Vec<String> {
+    let (dir, name) = match partial.rfind('/') {
+        Some(i) => partial.split_at(i + 1),
+        None => ("", partial),
+    };
+    let read_from = resolve_path(dir, base);
+    let Ok(entries) = std::fs::read_dir(&read_from) else {
+        return vec![];
+    };
+    let mut completions = entries
+        .filter_map(|e| e.ok())
+        .filter_map(|e| {
+            let entry = e.file_name().to_str()?.to_string();
+            // hidden ones only when asked for
+            if !entry.starts_with(name) || (entry.starts_with('.') && !name.starts_with('.')) {
+                return None;
+            }
+            let slash = if e.path().is_dir() { "/" } else { "" };
+            Some(format!("{}{}{}", dir, entry, slash))
+        })
+        .collect::<Vec<_>>();
+    completions.sort();
+    completions
+}
+
+/// candidate passwords one per line, blank lines are skipped
+fn load_candidates(file: &str) -> VecDeque<String> {
+    match std::fs::read_to_string(file) {
+        Ok(content) => content
+            .lines()
+            .map(|line| line.trim().to_string())
+            .filter(|line| !line.is_empty())
+            .collect(),
+        Err(e) => {
+            error!("read candidate passwords {} error: {}", file, e);
+            VecDeque::new()
+        }
+    }
+}
+
+/// spawn the opener and reap it in background, the opener may never exit
+fn open_destination(opener: &str, path: &Path) {
+    match Command::new(opener)
+        .arg(path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+    {
+        Ok(mut child) => {
+            tokio::spawn(async move {
+                if let Err(e) = child.wait().await {
+                    error!("wait opener error: {}", e);
+                }
+            });
+        }
+        Err(e) => error!("open destination with {} error: {}", opener, e),
+    }
+}
+
+// a long list is cut, one line is all we have
+const CONFLICTS_SHOWN: usize = 10;
+
+/// a second execute after it is taken as a new first one
+const CONFIRM_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// with --confirm-execute, the first execute arms and the second one runs it
+#[derive(Default)]
+struct ExecuteConfirm {
+    armed: Option<Instant>,
+}
+
+impl ExecuteConfirm {
+    /// true if the execute at now should run
+    fn confirm(&mut self, now: Instant) -> bool {
+        match self.armed.take() {
+            Some(at) if now.duration_since(at) <= CONFIRM_TIMEOUT => true,
+            _ => {
+                self.armed = Some(now);
+                false
+            }
+        }
+    }
+}
+
+/// "path  hash" rows, the path relative to target, a file that can not be read gets the error
+fn checksum_rows(target: &Path, files: &[(String, PathBuf)], kind: HashKind) -> Vec<String> {
+    files
+        .iter()
+        .map(|(_, dest)| {
+            let path = dest.strip_prefix(target).unwrap_or(dest);
+            let hash = file_checksum(dest, kind).unwrap_or_else(|e| format!("error: {}", e));
+            format!("{}  {}", path.display(), hash)
+        })
+        .collect()
+}
+
+/// "340ms", "3.4s", "2min 5s"
+fn elapsed_text(elapsed: Duration) -> String {
+    let millis = elapsed.as_millis();
+    if millis < 1000 {
+        format!("{}ms", millis)
+    } else if millis < 60_000 {
+        format!("{:.1}s", elapsed.as_secs_f64())
+    } else {
+        let secs = elapsed.as_secs();
+        format!("{}min {}s", secs / 60, secs % 60)
+    }
+}
+
+fn conflicts_line(conflicts: &[PathBuf]) -> String {
+    let mut shown = conflicts
+        .iter()
+        .take(CONFLICTS_SHOWN)
+        .map(|p| p.to_string_lossy().to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+    if conflicts.len() > CONFLICTS_SHOWN {
+        shown.push_str(&format!(
+            " ... and {} more",
+            conflicts.len() - CONFLICTS_SHOWN
+        ));
+    }
+    format!("Existing files: {}", shown)
+}
+
+fn mask(pwd: &str) -> String {
+    "*".repeat(pwd.chars().count())
+}
+
+/// the password of a "1: secret" choice, it is looked up by the number
+/// so a masked choice selects the real password
+fn history_password(file: &str, choice: &str) -> Option<String> {
+    let index: usize = choice.split(':').next()?.trim().parse().ok()?;
+    std::fs::read_to_string(file)
+        .ok()?
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .nth(index.checked_sub(1)?)
+        .map(|line| line.to_string())
+}
+
+const LAST_EXTRACT_PATH_FILE: &str = "last_extract_path.txt";
+
+/// read the remembered extract directory,
+/// ignore it if the directory is gone or not writable
+fn load_last_extract_path(file: &Path) -> Option<PathBuf> {
+    let path = std::fs::read_to_string(file).ok()?;
+    let path = PathBuf::from(path.trim());
+    // the same write probe as the extract path, a gone directory is not created again
+    if path.is_dir() && check_extract_path(&path).is_ok() {
+        Some(path)
+    } else {
+        info!("ignore last extract path: {:?}", path);
+        None
+    }
+}
+
+/// --output-dir from the working directory
+fn output_dir(opt: &Options) -> Option<PathBuf> {
+    let dir = opt.output_dir.as_ref()?;
+    let cwd = std::env::current_dir().unwrap_or_default();
+    Some(resolve_path(dir, &cwd))
+}
+
+/// check --output-dir before starting, as an extract would do
+pub fn check_output_dir(opt: &Options) -> Result<(), String> {
+    match output_dir(opt) {
+        Some(dir) => check_extract_path(&dir),
+        None => Ok(()),
+    }
+}
+
+/// the destination, or the nearest existing parent 7z will create it from, must be writable
+fn check_extract_path(path: &Path) -> Result<(), String> {
+    if path.exists() && !path.is_dir() {
+        return Err(format!(
+            "extract path is not a directory: {}",
+            path.display()
+        ));
+    }
+    let Some(existing) = path.ancestors().find(|p| p.exists()) else {
+        return Err(format!("extract path does not exist: {}", path.display()));
+    };
+    if !existing.is_dir() {
+        return Err(format!("not a directory: {}", existing.display()));
+    }
+    // the permission bits do not tell about the effective user, try to write instead
+    let probe = existing.join(format!(".vui-7z-write-test-{}", std::process::id()));
+    match std::fs::File::create(&probe) {
+        Ok(_) => {
+            let _ = std::fs::remove_file(&probe);
+            Ok(())
+        }
+        Err(e) => Err(format!("can not write to {}: {}", existing.display(), e)),
+    }
+}
+
+/// "ustar" at offset 257 of the first header, both POSIX and GNU tar have it
+fn is_tar(path: &Path) -> bool {
+    use std::io::Read;
+    let mut header = [0u8; 262];
+    std::fs::File::open(path)
+        .and_then(|mut f| f.read_exact(&mut header))
+        .is_ok_and(|_| &header[257..262] == b"ustar")
+}
+
+/// the "Key = Value" block of the path in "l -slt" output, blocks are after "----------"
+fn detail_lines(output: &str, path: &str) -> Vec<String> {
+    let path_line = format!("Path = {}", path);
+    let files = output
+        .split_once("----------")
+        .map_or("", |(_, files)| files);
+    // blocks are separated by an empty line, "\r\n" works too
+    let mut blocks = vec![vec![]];
+    for line in files.lines().map(|l| l.trim_end()) {
+        if line.is_empty() {
+            blocks.push(vec![]);
+        } else {
+            blocks.last_mut().unwrap().push(line.to_string());
+        }
+    }
+    blocks
+        .into_iter()
+        .find(|block| block.first() == Some(&path_line))
+        .unwrap_or_else(|| vec![format!("no details of {}", path)])
+}
+
+/// "-" is stdout
+fn export_text(path: &Path, text: &str) -> std::io::Result<()> {
+    if path == Path::new("-") {
+        let mut out = std::io::stdout().lock();
+        write!(out, "{}", text)?;
+        out.flush()
+    } else {
+        std::fs::write(path, text)
+    }
+}
+
+/// an ERROR: line for ErrorLB, with a hint when the 7z binary is missing
+fn spawn_error_line(bin: &str, e: &std::io::Error) -> String {
+    if e.kind() == ErrorKind::NotFound {
+        format!(
+            "ERROR: 7z binary not found: {}, install p7zip or set it with --bin",
+            bin
+        )
+    } else {
+        format!("ERROR: failed to run {}: {}", bin, e)
+    }
+}
+
+/// file names in UTF-8 whatever the locale is, 7z on windows may still print with
+/// the console code page, where --7z-arg=-sccUTF-8 is the one to try
+const FORCE_UTF8: &str = "-scsUTF-8";
+
+/// switches the commands already manage, passing them again would conflict
+const MANAGED_SWITCHES: [&str; 4] = ["-p", "-o", "-ao", "-bsp"];
+
+/// extra args must be switches, a positional one would be taken as a file
+pub fn check_extra_args(args: &[String]) -> Result<(), String> {
+    for arg in args {
+        if !arg.starts_with('-') || arg == "--" {
+            return Err(format!("--7z-arg must be a switch: {}", arg));
+        }
+        if let Some(switch) = MANAGED_SWITCHES.iter().find(|s| arg.starts_with(*s)) {
+            return Err(format!(
+                "--7z-arg {} conflicts with {} set by vui-7z",
+                arg, switch
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// look up the 7z binary before starting, a name is searched in PATH
+pub fn find_binary(bin: &str) -> Option<PathBuf> {
+    let path = Path::new(bin);
+    if path.components().count() > 1 {
+        return path.is_file().then(|| path.to_path_buf());
+    }
+    std::env::var_os("PATH").and_then(|paths| {
+        std::env::split_paths(&paths)
+            .map(|dir| dir.join(bin))
+            .find(|file| file.is_file())
+    })
+}
+
+fn spawn_cmd<I>(bin: &str, args: I) -> tokio::io::Result<Child>
+where
+    I: IntoIterator,
+    I::Item: AsRef<OsStr>,
+{
+    Command::new(bin)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        // the last resort if the task holding it is dropped
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| {
+            if e.kind() == ErrorKind::NotFound {
+                error!("7z binary not found: {}, try --bin to specify it", bin);
+                std::io::Error::new(ErrorKind::NotFound, format!("7z binary not found: {}", bin))
+            } else {
+                error!("failed to spawn {}: {}", bin, e);
+                e
+            }
+        })
+}
+
+/// (line, from stdout:1 or stderr:2)
+type OutputLine = (Vec<u8>, usize);
+
+/// the outer archive while a nested one is browsed
+struct ArchiveContext {
+    file: String,
+    password: Option<String>,
+    extract_to_path: PathBuf,
+    wrap_dir: bool,
+    // the nested archive was extracted here, removed when leaving it
+    temp_dir: TempDir,
+}
+
+/// a fresh dir only we can write, never a guessed path another user may have made
+fn temp_dir(prefix: &str) -> std::io::Result<TempDir> {
+    tempfile::Builder::new().prefix(prefix).tempdir()
+}
+
+fn remove_temp_dir(dir: TempDir) {
+    let path = dir.path().display().to_string();
+    if let Err(e) = dir.close() {
+        error!("remove temp dir {} error: {}", path, e);
+    }
+}
+
+/// by the extension, 7z alone knows for sure but probing every row is too slow
+fn is_archive_name(name: &str) -> bool {
+    if name.ends_with('/') {
+        return false;
+    }
+    Path::new(name)
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| ARCHIVE_EXTENSIONS.contains(&e.to_lowercase().as_str()))
+}
+
+const ARCHIVE_EXTENSIONS: [&str; 20] = [
+    "7z", "zip", "rar", "tar", "gz", "tgz", "bz2", "tbz2", "xz", "txz", "zst", "lzma", "cab",
+    "iso", "wim", "jar", "apk", "cpio", "arj", "lzh",
+];
+
+/// output lines of a successful list, valid while the archive keeps its mtime
+struct ListCache {
+    file: String,
+    modified: SystemTime,
+    lines: Vec<OutputLine>,
+}
+
+impl ListCache {
+    fn hit(&self, file: &str, modified: SystemTime) -> bool {
+        self.file == file && self.modified == modified
+    }
+}
+
+fn modified_time(file: &str) -> Option<SystemTime> {
+    std::fs::metadata(file).and_then(|m| m.modified()).ok()
+}
+
+/// pass the output on to read_document, the lines are kept for the list cache
+fn record_output(
+    opt_sender: mpsc::Sender<Option<OutputLine>>,
+) -> (
+    mpsc::Sender<Option<OutputLine>>,
+    tokio::task::JoinHandle<Vec<OutputLine>>,
+) {
+    let (sender, mut recv) = mpsc::channel::<Option<OutputLine>>(1);
+    let recorded = tokio::spawn(async move {
+        let mut lines = vec![];
+        while let Some(line) = recv.recv().await {
+            if let Some(line) = &line {
+                lines.push(line.clone());
+            }
+            if opt_sender.send(line).await.is_err() {
+                break;
+            }
+        }
+        lines
+    });
+    (sender, recorded)
+}
+
+/// feed cached lines to read_document as if 7z printed them, then the EOF
+async fn replay_output(
+    lines: &[OutputLine],
+    opt_sender: mpsc::Sender<Option<OutputLine>>,
+) -> tokio::io::Result<ExitStatus> {
+    for line in lines {
+        if opt_sender.send(Some(line.clone())).await.is_err() {
+            return Err(ErrorKind::BrokenPipe.into());
+        }
+    }
+    if opt_sender.send(None).await.is_err() {
+        return Err(ErrorKind::BrokenPipe.into());
+    }
+    // the default is a success
+    Ok(ExitStatus::default())
+}
+
+/// what every 7z command runs with,
+/// the child is kept in running and its stdin in stdin_pipe while it runs
+struct ExecuteContext<'a> {
+    bin: &'a str,
+    extra_args: &'a [String],
+    opt_sender: mpsc::Sender<Option<(Vec<u8>, usize)>>,
+    stdin_pipe: Arc<RwLock<Option<ChildStdin>>>,
+    running: Arc<RwLock<Option<Child>>>,
+}
+
+async fn execute_cmd<I>(ctx: ExecuteContext<'_>, args: I) -> tokio::io::Result<ExitStatus>
+where
+    I: IntoIterator,
+    I::Item: AsRef<OsStr>,
+{
+    let ExecuteContext {
+        bin,
+        extra_args,
+        opt_sender,
+        stdin_pipe,
+        running,
+    } = ctx;
+    let mut args = args
+        .into_iter()
+        .map(|a| a.as_ref().to_os_string())
+        .collect::<Vec<_>>();
+    // switches right after the command, before the archive, "-p" and "--"
+    let at = args.len().min(1);
+    args.splice(at..at, extra_args.iter().map(OsString::from));
+    let mut child = spawn_cmd(bin, args)?;
+    // set stdin to Z7.stdin_pipe
+    stdin_pipe
+        .write()
+        .await
+        .replace(child.stdin.take().unwrap());
+    let stdout = child.stdout.take().unwrap();
+    let stderr = child.stderr.take().unwrap();
+    // keep the child in Z7.running, so it can be killed by another command
+    running.write().await.replace(child);
+
+    // a killed child closes its output, read_output get EOF then return
+    read_output(stdout, stderr, opt_sender.clone()).await?;
+    let child = running.write().await.take();
+    match child {
+        Some(mut child) => child.wait().await,
+        None => Err(ErrorKind::Interrupted.into()),
+    }
+}
+
+/// kill the running 7z process, the waiting execute_cmd will get its status
+async fn kill_running(running: &Arc<RwLock<Option<Child>>>) {
+    let mut running = running.write().await;
+    if let Some(child) = running.as_mut() {
+        info!("kill running 7z: {:?}", child.id());
+        if let Err(e) = child.kill().await {
+            error!("kill running 7z error: {}", e);
+        }
+    }
+}
+
+async fn execute_list(
+    ctx: ExecuteContext<'_>,
+    filename: &str,
+    password: Option<String>,
+) -> tokio::io::Result<ExitStatus> {
+    let mut args = vec!["l", filename];
+    let pwd = password.map(|s| format!("-p{}", s));
+    if let Some(w) = pwd.as_ref() {
+        args.push(w);
+    }
+    execute_cmd(ctx, args).await
+}
+
+async fn execute_technical_list(
+    ctx: ExecuteContext<'_>,
+    filename: &str,
+    password: Option<String>,
+) -> tokio::io::Result<ExitStatus> {
+    let mut args = vec!["l", "-slt", filename];
+    let pwd = password.map(|s| format!("-p{}", s));
+    if let Some(w) = pwd.as_ref() {
+        args.push(w);
+    }
+    execute_cmd(ctx, args).await
+}
+
+/// "e" extracts every file into extract_to_path directly, "x" keeps the paths
+fn extract_command(flatten: bool) -> &'static str {
+    if flatten {
+        "e"
+    } else {
+        "x"
+    }
+}
+
+async fn execute_extract(
+    ctx: ExecuteContext<'_>,
+    filename: &str,
+    password: Option<String>,
+    extract_to_path: &str,
+    flatten: bool,
+    overwrite: OverwriteMode,
+) -> tokio::io::Result<ExitStatus> {
+    let out = format!("-o{}", extract_to_path);
+    let mut args = vec![
+        extract_command(flatten),
+        filename,
+        "-y",
+        // progress frames on stdout, they are redrawn with backspaces
+        "-bsp1",
+        &out,
+        overwrite.switch(),
+    ];
+    let pwd = password.map(|s| format!("-p{}", s));
+    if let Some(w) = pwd.as_ref() {
+        args.push(w);
+    }
+    execute_cmd(ctx, args).await
+}
+
+async fn execute_extract_files(
+    ctx: ExecuteContext<'_>,
+    filename: &str,
+    password: Option<String>,
+    extract_to_path: &str,
+    flatten: bool,
+    overwrite: OverwriteMode,
+    files: &[String],
+) -> tokio::io::Result<ExitStatus> {
+    let out = format!("-o{}", extract_to_path);
+    let mut args = vec![
+        extract_command(flatten),
+        filename,
+        "-y",
+        // progress frames on stdout, they are redrawn with backspaces
+        "-bsp1",
+        &out,
+        overwrite.switch(),
+    ];
+    let pwd = password.map(|s| format!("-p{}", s));
+    if let Some(w) = pwd.as_ref() {
+        args.push(w);
+    }
+    // stop parsing switches, a file name may start with '-'
+    args.push("--");
+    args.extend(files.iter().map(|f| f.as_str()));
+    execute_cmd(ctx, args).await
+}
+
+async fn execute_add(
+    ctx: ExecuteContext<'_>,
+    filename: &str,
+    password: Option<String>,
+    encrypt_headers: bool,
+    paths: &[String],
+) -> tokio::io::Result<ExitStatus> {
+    let mut args = vec!["a", filename];
+    let pwd = password.map(|s| format!("-p{}", s));
+    if let Some(w) = pwd.as_ref() {
+        args.push(w);
+        if encrypt_headers {
+            // the file names are hidden too, listing asks for the password
+            args.push("-mhe=on");
+        }
     }
+    args.push("--");
+    args.extend(paths.iter().map(|p| p.as_str()));
+    execute_cmd(ctx, args).await
 }
 
-fn spawn_cmd<I>(args: I) -> tokio::io::Result<Child>
-where
-    I: IntoIterator,
-    I::Item: AsRef<OsStr>,
-{
-    Command::new("7z")
-        .args(args)
-        .stdin(Stdio::piped())
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn()
+/// only the 7z format can encrypt its headers, 7z infers the format from the extension
+fn encrypt_headers(filename: &str) -> bool {
+    filename.to_lowercase().ends_with(".7z")
 }
 
-async fn execute_cmd<I>(
-    opt_sender: mpsc::Sender<Option<(Vec<u8>, usize)>>,
-    stdin_pipe: Arc<RwLock<Option<ChildStdin>>>,
-    args: I,
-) -> tokio::io::Result<ExitStatus>
-where
-    I: IntoIterator,
-    I::Item: AsRef<OsStr>,
-{
-    let mut child = spawn_cmd(args)?;
-    // set stdin to Z7.stdin_pipe
-    stdin_pipe
-        .write()
-        .await
-        .replace(child.stdin.take().unwrap());
+/// "Set encryption: headers" | "Set encryption: files" | "Set encryption: off"
+fn encryption_input(encrypt: bool, headers: bool) -> String {
+    let state = match (encrypt, headers) {
+        (false, _) => "off",
+        (true, false) => "files",
+        (true, true) => "headers",
+    };
+    format!("Set encryption: {}", state)
+}
 
-    read_output(
-        child.stdout.take().unwrap(),
-        child.stderr.take().unwrap(),
-        opt_sender.clone(),
-    )
-    .await?;
-    child.wait().await
+async fn execute_delete(
+    ctx: ExecuteContext<'_>,
+    filename: &str,
+    password: Option<String>,
+    files: &[String],
+) -> tokio::io::Result<ExitStatus> {
+    let mut args = vec!["d", filename];
+    let pwd = password.map(|s| format!("-p{}", s));
+    if let Some(w) = pwd.as_ref() {
+        args.push(w);
+    }
+    args.push("--");
+    args.extend(files.iter().map(|f| f.as_str()));
+    execute_cmd(ctx, args).await
 }
 
-async fn execute_list(
+async fn execute_rename(
+    ctx: ExecuteContext<'_>,
     filename: &str,
-    opt_sender: mpsc::Sender<Option<(Vec<u8>, usize)>>,
-    stdin_pipe: Arc<RwLock<Option<ChildStdin>>>,
     password: Option<String>,
+    (old, new): (&str, &str),
 ) -> tokio::io::Result<ExitStatus> {
-    let mut args = vec!["l", filename];
+    let mut args = vec!["rn", filename];
     let pwd = password.map(|s| format!("-p{}", s));
     if let Some(w) = pwd.as_ref() {
         args.push(w);
     }
-    execute_cmd(opt_sender, stdin_pipe, args).await
+    args.extend(["--", old, new]);
+    execute_cmd(ctx, args).await
 }
 
-async fn execute_extract(
+async fn execute_test(
+    ctx: ExecuteContext<'_>,
     filename: &str,
-    opt_sender: mpsc::Sender<Option<(Vec<u8>, usize)>>,
-    stdin_pipe: Arc<RwLock<Option<ChildStdin>>>,
     password: Option<String>,
-    extract_to_path: &str,
 ) -> tokio::io::Result<ExitStatus> {
-    let out = format!("-o{}", extract_to_path);
-    let mut args = vec!["x", filename, "-y", &out];
+    let mut args = vec!["t", filename];
     let pwd = password.map(|s| format!("-p{}", s));
     if let Some(w) = pwd.as_ref() {
         args.push(w);
     }
-    execute_cmd(opt_sender, stdin_pipe, args).await
+    execute_cmd(ctx, args).await
 }
 
+async fn execute_info(ctx: ExecuteContext<'_>) -> tokio::io::Result<ExitStatus> {
+    execute_cmd(ctx, ["i"]).await
+}
+
+/// lines of both streams, tagged 1 for stdout and 2 for stderr, only the log reads the tag,
+/// p7zip prints "Enter password" on stdout while other 7z builds use stderr,
+/// so it is told by the text, on either of them
 async fn read_output<O, E>(
     stdout: O,
     stderr: E,
     opt_sender: mpsc::Sender<Option<(Vec<u8>, usize)>>,
 ) -> tokio::io::Result<()>
 where
-    O: AsyncReadExt + Unpin,
-    E: AsyncReadExt + Unpin,
+    O: AsyncRead + Unpin,
+    E: AsyncRead + Unpin,
 {
     let mut reader = OutputReader::new(stdout, stderr);
     // stdout , stderr
     let mut bufs = [vec![], vec![]];
+    // the line before the running backspaces, a frame once they erased all of it
+    let mut erased: [Option<Vec<u8>>; 2] = [None, None];
     loop {
         match reader.read().await {
-            Ok((c, from)) => {
-                // 'LF'
-                if c == 0x0a {
-                    let buf = std::mem::take(&mut bufs[from]);
-                    opt_sender
-                        .send(Some((buf, from + 1)))
-                        .await
-                        .expect("send string line error");
-                }
-                // '\b' backspace, actually someone eat them
-                else if c == 0x08 {
-                    info!("read output has backspace");
-                }
-                // ':'
-                else if c == 0x3a && bufs[from].starts_with("Enter password".as_bytes()) {
-                    bufs[from].push(c);
-                    let buf = std::mem::take(&mut bufs[from]);
-                    opt_sender
-                        .send(Some((buf, from + 1)))
-                        .await
-                        .expect("send string line error");
-                } else {
-                    bufs[from].push(c);
+            Ok((chunk, from)) => {
+                for c in chunk {
+                    if c != 0x08 {
+                        erased[from] = None;
+                    }
+                    // 'LF'
+                    if c == 0x0a {
+                        let buf = std::mem::take(&mut bufs[from]);
+                        opt_sender
+                            .send(Some((buf, from + 1)))
+                            .await
+                            .expect("send string line error");
+                    }
+                    // 'CR', 7z redraws progress with it, treat it as a line end
+                    else if c == 0x0d {
+                        if !bufs[from].is_empty() {
+                            let buf = std::mem::take(&mut bufs[from]);
+                            opt_sender
+                                .send(Some((buf, from + 1)))
+                                .await
+                                .expect("send string line error");
+                        }
+                    }
+                    // '\b' backspace, takes back the last char like a terminal does,
+                    // -bsp1 erases the whole progress frame with them, then the frame is a line,
+                    // the blanks that wipe it are dropped
+                    else if c == 0x08 {
+                        let line = erased[from].get_or_insert_with(|| bufs[from].clone());
+                        pop_char(&mut bufs[from]);
+                        if bufs[from].is_empty() {
+                            let frame = std::mem::take(line);
+                            erased[from] = None;
+                            if !frame.iter().all(|b| b.is_ascii_whitespace()) {
+                                opt_sender
+                                    .send(Some((frame, from + 1)))
+                                    .await
+                                    .expect("send string line error");
+                            }
+                        }
+                    }
+                    // ':', the password prompt has no line end, whichever stream it is on
+                    else if c == 0x3a && bufs[from].starts_with("Enter password".as_bytes()) {
+                        bufs[from].push(c);
+                        let buf = std::mem::take(&mut bufs[from]);
+                        opt_sender
+                            .send(Some((buf, from + 1)))
+                            .await
+                            .expect("send string line error");
+                    } else {
+                        bufs[from].push(c);
+                    }
                 }
             }
             // EOF
@@ -508,49 +2751,152 @@ where
     Ok(())
 }
 
+/// the last UTF-8 char, its continuation bytes go with it
+fn pop_char(buf: &mut Vec<u8>) {
+    while let Some(b) = buf.pop() {
+        if b & 0xC0 != 0x80 {
+            break;
+        }
+    }
+}
+
 /// read the stdout and stderr from child process
 /// hold EOF one of them, util both of them are EOF
 struct OutputReader<O, E> {
-    stdout: O,
-    stderr: E,
+    stdout: BufReader<O>,
+    stderr: BufReader<E>,
     eof: [bool; 2],
 }
 
-impl<O, E> OutputReader<O, E> {
+impl<O, E> OutputReader<O, E>
+where
+    O: AsyncRead + Unpin,
+    E: AsyncRead + Unpin,
+{
     fn new(stdout: O, stderr: E) -> Self {
         Self {
-            stdout,
-            stderr,
+            stdout: BufReader::new(stdout),
+            stderr: BufReader::new(stderr),
             eof: [false; 2],
         }
     }
-}
 
-impl<O, E> OutputReader<O, E>
-where
-    O: AsyncReadExt + Unpin,
-    E: AsyncReadExt + Unpin,
-{
-    async fn read(&mut self) -> tokio::io::Result<(u8, usize)> {
+    /// whatever is buffered from one of them, EOF of one stream is a line end
+    async fn read(&mut self) -> tokio::io::Result<(Vec<u8>, usize)> {
         let r = select! {
-            c = self.stdout.read_u8(), if !self.eof[0] => (c, 0),
-            c = self.stderr.read_u8(), if !self.eof[1] => (c, 1),
+            r = self.stdout.fill_buf(), if !self.eof[0] => (r.map(|b| b.to_vec()), 0),
+            r = self.stderr.fill_buf(), if !self.eof[1] => (r.map(|b| b.to_vec()), 1),
         };
         match r {
-            (Ok(c), p) => Ok((c, p)),
-            (Err(e), from) => {
+            (Ok(chunk), from) if !chunk.is_empty() => {
+                if from == 0 {
+                    self.stdout.consume(chunk.len());
+                } else {
+                    self.stderr.consume(chunk.len());
+                }
+                Ok((chunk, from))
+            }
+            (r, from) => {
                 self.eof[from] = true;
                 if self.eof[0] && self.eof[1] {
-                    Err(e)
+                    Err(r.err().unwrap_or_else(|| ErrorKind::UnexpectedEof.into()))
                 } else {
-                    Ok((0x0a, from))
+                    Ok((vec![0x0a], from))
                 }
             }
         }
     }
 }
 
+/// "Set wrap dir: <stem>" | "Set wrap dir: off" for the document
+fn wrap_dir_input(wrapped: bool, stem: &str) -> String {
+    if wrapped {
+        format!("Set wrap dir: {}", stem)
+    } else {
+        "Set wrap dir: off".to_string()
+    }
+}
+
+/// the archive name without extensions and volume suffix,
+/// "archive.7z.001" -> "archive", "archive.tar.gz" -> "archive"
+pub fn archive_stem(file: &str) -> String {
+    let mut path = PathBuf::from(PathBuf::from(file).file_name().unwrap());
+    // volume suffix .001, .002
+    if path
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| !e.is_empty() && e.bytes().all(|b| b.is_ascii_digit()))
+    {
+        path = PathBuf::from(path.file_stem().unwrap());
+    }
+    let mut stem = PathBuf::from(path.file_stem().unwrap());
+    if stem.extension().is_some_and(|e| e == "tar") {
+        stem = PathBuf::from(stem.file_stem().unwrap());
+    }
+    stem.to_str().unwrap().to_string()
+}
+
+/// (prefix, suffix) around the volume number,
+/// "a.7z.001" -> ("a.7z.", ""), "a.part1.rar" -> ("a.part", ".rar")
+fn volume_pattern(name: &str) -> Option<(&str, &str)> {
+    let is_number = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+    if let Some((prefix, number)) = name.rsplit_once('.') {
+        if is_number(number) {
+            return Some((&name[..prefix.len() + 1], ""));
+        }
+    }
+    // ascii only, the byte offsets stay the same
+    let lower = name.to_ascii_lowercase();
+    let stem = lower.strip_suffix(".rar")?;
+    let at = stem.rfind(".part")? + ".part".len();
+    is_number(&stem[at..]).then(|| (&name[..at], &name[stem.len()..]))
+}
+
+/// the split volumes next to the archive with their sizes, sorted by the number,
+/// empty for an archive of a single file
+fn find_volumes(file: &Path) -> Vec<(String, u64)> {
+    let Some(name) = file.file_name().and_then(|n| n.to_str()) else {
+        return vec![];
+    };
+    let Some((prefix, suffix)) = volume_pattern(name) else {
+        return vec![];
+    };
+    let dir = match file.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return vec![];
+    };
+    let mut volumes = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            let number = name
+                .strip_prefix(prefix)?
+                .strip_suffix(suffix)?
+                .parse::<u64>()
+                .ok()?;
+            let size = entry.metadata().ok().filter(|m| m.is_file())?.len();
+            Some((number, name, size))
+        })
+        .collect::<Vec<_>>();
+    if volumes.len() < 2 {
+        return vec![];
+    }
+    volumes.sort();
+    volumes
+        .into_iter()
+        .map(|(_, name, size)| (name, size))
+        .collect()
+}
+
+/// archives made on windows may list "test\a.png", the prefix is normalized to "test/"
 pub fn check_same_directory(files: &[String]) -> Option<String> {
+    let files = files
+        .iter()
+        .map(|f| f.replace('\\', "/"))
+        .collect::<Vec<_>>();
     let mut prefix = String::new();
     let mut iter = files.iter();
     if let Some(first) = iter.next() {
@@ -582,7 +2928,67 @@ pub fn check_same_directory(files: &[String]) -> Option<String> {
 
 #[cfg(test)]
 mod test {
-    use super::check_same_directory;
+    use std::{os::unix::process::ExitStatusExt, process::ExitStatus};
+
+    use std::{
+        path::PathBuf,
+        sync::Arc,
+        time::{Duration, Instant, SystemTime},
+    };
+
+    use tokio::sync::{mpsc, RwLock};
+
+    use clap::{CommandFactory, FromArgMatches};
+
+    use crate::{
+        options::Options,
+        output_format::{Document, Failure},
+    };
+
+    use super::{
+        archive_stem, check_extra_args, check_extract_path, check_output_dir, check_same_directory,
+        complete_path, detail_lines, elapsed_text, execute_cmd, expand_path, find_binary,
+        find_volumes, history_password, is_archive_name, is_tar, kill_running, load_candidates,
+        load_last_extract_path, mask, output_dir, read_output, record_output, replay_output,
+        resolve_path, spawn_error_line, user_home, Cmd, ExecuteConfirm, ExecuteContext,
+        ExecuteStatus, ExitCode, ListCache, Operation, Pushment, CONFIRM_TIMEOUT, Z7,
+    };
+
+    #[test]
+    fn test_is_archive_name() {
+        assert!(is_archive_name("dir/inner.zip"));
+        assert!(is_archive_name("BACKUP.7Z"));
+        assert!(is_archive_name("a.tar.gz"));
+        assert!(!is_archive_name("readme.txt"));
+        assert!(!is_archive_name("tools.zip/"));
+        assert!(!is_archive_name("Makefile"));
+    }
+
+    #[tokio::test]
+    async fn test_end_peek() {
+        let matches = Options::command()
+            .try_get_matches_from(["vui-7z", "test.7z", "-o", "/tmp/out", "--no-remember-path"])
+            .unwrap();
+        let opt = Options::from_arg_matches(&matches).unwrap();
+        let (sender, mut recv) = mpsc::channel(1);
+        let z = Z7::new(sender, &opt);
+        assert!(z.end_peek().await.is_none());
+        let dir = std::env::temp_dir().join("vui-7z-peek-test");
+        let previous = std::mem::replace(&mut *z.extract_to_path.write().await, dir.clone());
+        z.peeking.write().await.replace(previous);
+        assert_eq!(z.end_peek().await, Some(dir));
+        assert_eq!(*z.extract_to_path.read().await, PathBuf::from("/tmp/out"));
+        assert!(
+            matches!(recv.recv().await, Some(Pushment::Line(4, l)) if l == "Extract to: /tmp/out")
+        );
+    }
+
+    #[test]
+    fn test_elapsed_text() {
+        assert_eq!(elapsed_text(Duration::from_millis(340)), "340ms");
+        assert_eq!(elapsed_text(Duration::from_millis(3420)), "3.4s");
+        assert_eq!(elapsed_text(Duration::from_secs(125)), "2min 5s");
+    }
 
     #[test]
     fn test_path_parent() {
@@ -594,6 +3000,95 @@ mod test {
         assert_eq!(parent, std::path::PathBuf::from("code/vui-7z"));
     }
 
+    #[test]
+    fn test_expand_path() {
+        let home = std::env::var("HOME").unwrap();
+        assert_eq!(expand_path("~"), home);
+        assert_eq!(expand_path("~/out"), format!("{}/out", home));
+        if let Some(root) = user_home("root") {
+            assert_eq!(expand_path("~root/out"), format!("{}/out", root));
+        }
+        assert_eq!(
+            expand_path("~vui-7z-no-such-user/out"),
+            "~vui-7z-no-such-user/out"
+        );
+        assert_eq!(expand_path("$HOME/out"), format!("{}/out", home));
+        assert_eq!(expand_path("${HOME}/out"), format!("{}/out", home));
+        assert_eq!(expand_path("/a/$HOME"), format!("/a/{}", home));
+        assert_eq!(expand_path("$VUI_7Z_NOT_SET/out"), "$VUI_7Z_NOT_SET/out");
+        assert_eq!(expand_path("/tmp/$/${x"), "/tmp/$/${x");
+        assert_eq!(expand_path("/tmp/~"), "/tmp/~");
+
+        let base = std::path::Path::new("/home/someone/download");
+        assert_eq!(
+            resolve_path("/tmp/out", base),
+            std::path::PathBuf::from("/tmp/out")
+        );
+        assert_eq!(
+            resolve_path("out/a", base),
+            std::path::PathBuf::from("/home/someone/download/out/a")
+        );
+        assert_eq!(
+            resolve_path("$HOME/out", base),
+            std::path::PathBuf::from(format!("{}/out", home))
+        );
+    }
+
+    #[test]
+    fn test_is_tar() {
+        let file = std::env::temp_dir().join("vui-7z-test-is-tar");
+        let mut header = vec![0u8; 512];
+        header[257..262].copy_from_slice(b"ustar");
+        std::fs::write(&file, &header).unwrap();
+        assert!(is_tar(&file));
+        std::fs::write(&file, "not a tar").unwrap();
+        assert!(!is_tar(&file));
+        std::fs::remove_file(&file).unwrap();
+    }
+
+    #[test]
+    fn test_check_extract_path() {
+        let dir = std::env::temp_dir().join("vui-7z-test-extract-path");
+        std::fs::create_dir_all(&dir).unwrap();
+        assert!(check_extract_path(&dir).is_ok());
+        // 7z creates the missing folders
+        assert!(check_extract_path(&dir.join("a/b")).is_ok());
+        let file = dir.join("file");
+        std::fs::write(&file, "").unwrap();
+        assert!(check_extract_path(&file)
+            .unwrap_err()
+            .starts_with("extract path is not a directory"));
+        assert!(check_extract_path(&file.join("a"))
+            .unwrap_err()
+            .starts_with("not a directory"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_complete_path() {
+        let dir = std::env::temp_dir().join("vui-7z-test-complete");
+        std::fs::create_dir_all(dir.join("download")).unwrap();
+        std::fs::write(dir.join("docs.txt"), "").unwrap();
+        std::fs::write(dir.join(".hidden"), "").unwrap();
+        let prefix = format!("{}/", dir.to_str().unwrap());
+        assert_eq!(
+            complete_path(&format!("{}do", prefix), &dir),
+            [
+                format!("{}docs.txt", prefix),
+                format!("{}download/", prefix)
+            ]
+        );
+        assert_eq!(complete_path(&prefix, &dir).len(), 2);
+        // relative to the archive directory
+        assert_eq!(complete_path("dow", &dir), ["download/"]);
+        assert_eq!(
+            complete_path(&format!("{}.h", prefix), &dir),
+            [format!("{}.hidden", prefix)]
+        );
+        assert!(complete_path(&format!("{}nothing/", prefix), &dir).is_empty());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
     #[test]
     fn test_path_display() {
         let path = std::path::PathBuf::from("");
@@ -604,6 +3099,420 @@ mod test {
         assert_eq!(path.to_str().unwrap(), "/home/chen/code/vui-7z/src");
     }
 
+    #[tokio::test]
+    async fn test_kill_running() {
+        let (opt_sender, mut opt_recv) = mpsc::channel(1);
+        let stdin_pipe = Arc::new(RwLock::new(None));
+        let running = Arc::new(RwLock::new(None));
+        tokio::spawn(async move { while opt_recv.recv().await.is_some() {} });
+        let cmd = tokio::spawn(execute_cmd(
+            ExecuteContext {
+                bin: "sleep",
+                extra_args: &[],
+                opt_sender,
+                stdin_pipe,
+                running: running.clone(),
+            },
+            ["10"],
+        ));
+        while running.read().await.is_none() {
+            tokio::task::yield_now().await;
+        }
+        // rapid retries
+        for _ in 0..3 {
+            kill_running(&running).await;
+        }
+        let status = tokio::time::timeout(std::time::Duration::from_secs(2), cmd)
+            .await
+            .expect("killed command should finish")
+            .unwrap()
+            .unwrap();
+        assert!(!status.success());
+        assert!(running.read().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_read_output_utf8() {
+        let stdout = "------------------- ----- ------------ ------------  ------------------------
+2023-12-12 09:18:24 ....A       344963     13216256  蝶子系列/0001.jpg
+2023-12-12 09:18:28 ....A       821434               café/crème brûlée.png
+------------------- ----- ------------ ------------  ------------------------
+2023-12-22 16:17:58            1166397     13216256  2 files
+";
+        // an invalid byte in the middle of a name
+        let stderr: &[u8] = b"WARNING: bad \xff name\n";
+        let (opt_sender, mut opt_recv) = mpsc::channel(1);
+        let reader = tokio::spawn(read_output(stdout.as_bytes(), stderr, opt_sender));
+        let mut doc = Document::new();
+        let mut lines = vec![];
+        while let Some(Some((line, _))) = opt_recv.recv().await {
+            let line = String::from_utf8_lossy(&line).to_string();
+            doc.input(&line);
+            lines.push(line);
+        }
+        reader.await.unwrap().unwrap();
+        assert_eq!(
+            doc.files(),
+            vec!["蝶子系列/0001.jpg", "café/crème brûlée.png"]
+        );
+        assert!(lines.contains(&"WARNING: bad \u{FFFD} name".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_read_output_password_prompt() {
+        // the prompt has no line end, 7z waits for the password after ':'
+        let stdout: &[u8] = b"Scanning\rEnter password (will not be echoed):";
+        let (opt_sender, mut opt_recv) = mpsc::channel(1);
+        let reader = tokio::spawn(read_output(stdout, &b""[..], opt_sender));
+        let mut lines = vec![];
+        while let Some(Some((line, fd))) = opt_recv.recv().await {
+            // EOF of stderr may come first as an empty line
+            if fd == 1 {
+                lines.push(String::from_utf8_lossy(&line).to_string());
+            }
+        }
+        reader.await.unwrap().unwrap();
+        assert_eq!(lines[0], "Scanning");
+        assert_eq!(lines[1], "Enter password (will not be echoed):");
+    }
+
+    #[tokio::test]
+    async fn test_read_output_password_prompt_stderr() {
+        let stdout: &[u8] = b"Scanning the drive for archives:\n1 file, 2048 bytes\n";
+        let stderr: &[u8] = b"Enter password:";
+        let (opt_sender, mut opt_recv) = mpsc::channel(1);
+        let reader = tokio::spawn(read_output(stdout, stderr, opt_sender));
+        let mut doc = Document::new();
+        doc.layout_list();
+        let mut prompts = vec![];
+        while let Some(Some((line, fd))) = opt_recv.recv().await {
+            let line = String::from_utf8_lossy(&line).to_string();
+            if line.starts_with("Enter password") {
+                prompts.push((line.clone(), fd));
+            }
+            doc.input(&line);
+        }
+        reader.await.unwrap().unwrap();
+        assert_eq!(prompts, [("Enter password:".to_string(), 2)]);
+        // the document only sees the text
+        assert!(doc.password_line().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_read_output_progress() {
+        // -bsp1 redraws the frame in place, the blanks wipe the previous one
+        let stdout: &[u8] = b"  0%\x08\x08\x08\x08    \x08\x08\x08\x08 45% 1 - a.png\x08\x08\x08\x08\x08\x08\x08\x08\x08\x08\x08\x08\x08\x08Everything is Ok\n";
+        let (opt_sender, mut opt_recv) = mpsc::channel(1);
+        let reader = tokio::spawn(read_output(stdout, &b""[..], opt_sender));
+        let mut lines = vec![];
+        while let Some(Some((line, fd))) = opt_recv.recv().await {
+            if fd == 1 {
+                lines.push(String::from_utf8_lossy(&line).to_string());
+            }
+        }
+        reader.await.unwrap().unwrap();
+        assert_eq!(lines[..3], ["  0%", " 45% 1 - a.png", "Everything is Ok"]);
+    }
+
+    #[tokio::test]
+    async fn test_read_output_backspace() {
+        // a counter redrawn in place, a backspace takes a char, not a byte
+        let stdout = "Files: 9\x0810\nsize: 12%\x08\x08\x0813%\nname: 蝶子\x08\x08ab\n";
+        let (opt_sender, mut opt_recv) = mpsc::channel(1);
+        let reader = tokio::spawn(read_output(stdout.as_bytes(), &b""[..], opt_sender));
+        let mut lines = vec![];
+        while let Some(Some((line, fd))) = opt_recv.recv().await {
+            if fd == 1 {
+                lines.push(String::from_utf8_lossy(&line).to_string());
+            }
+        }
+        reader.await.unwrap().unwrap();
+        assert_eq!(lines[..3], ["Files: 10", "size: 13%", "name: ab"]);
+    }
+
+    #[test]
+    fn test_output_dir() {
+        let opt = |args: &[&str]| {
+            let matches = Options::command().try_get_matches_from(args).unwrap();
+            Options::from_arg_matches(&matches).unwrap()
+        };
+        let o = opt(&["vui-7z", "test.7z", "-o", "$HOME/out", "--no-remember-path"]);
+        let home = PathBuf::from(std::env::var("HOME").unwrap());
+        assert_eq!(output_dir(&o), Some(home.join("out")));
+        let z = Z7::new(mpsc::channel(1).0, &o);
+        assert_eq!(*z.extract_to_path.try_read().unwrap(), home.join("out"));
+        let o = opt(&["vui-7z", "test.7z", "--output-dir", "out"]);
+        assert_eq!(
+            output_dir(&o),
+            Some(std::env::current_dir().unwrap().join("out"))
+        );
+        assert!(output_dir(&opt(&["vui-7z", "test.7z"])).is_none());
+        assert!(check_output_dir(&opt(&["vui-7z", "test.7z"])).is_ok());
+        assert!(check_output_dir(&opt(&["vui-7z", "test.7z", "-o", "/dev/null"])).is_err());
+    }
+
+    #[test]
+    fn test_force_utf8() {
+        let z7 = |args: &[&str]| {
+            let matches = Options::command().try_get_matches_from(args).unwrap();
+            let opt = Options::from_arg_matches(&matches).unwrap();
+            Z7::new(mpsc::channel(1).0, &opt)
+        };
+        let z = z7(&["vui-7z", "test.7z", "--7z-arg=-mmt=4"]);
+        assert_eq!(z.extra_args(&Cmd::List), ["-mmt=4", "-scsUTF-8"]);
+        assert_eq!(z.extra_args(&Cmd::Test), ["-mmt=4"]);
+        let z = z7(&["vui-7z", "test.7z", "--no-force-utf8"]);
+        assert!(z.extra_args(&Cmd::Extract).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_extra_args() {
+        assert!(check_extra_args(&["-mmt=4".to_string(), "-snl".to_string()]).is_ok());
+        assert!(check_extra_args(&["file.txt".to_string()]).is_err());
+        assert!(check_extra_args(&["-psecret".to_string()]).is_err());
+        assert!(check_extra_args(&["-aoa".to_string()]).is_err());
+
+        let (opt_sender, mut opt_recv) = mpsc::channel::<Option<(Vec<u8>, usize)>>(1);
+        let output = tokio::spawn(async move {
+            let mut lines = vec![];
+            while let Some(line) = opt_recv.recv().await {
+                if let Some((line, 1)) = line {
+                    lines.push(String::from_utf8_lossy(&line).to_string());
+                }
+            }
+            lines
+        });
+        let extra_args = ["-mmt=4".to_string()];
+        let status = execute_cmd(
+            ExecuteContext {
+                bin: "echo",
+                extra_args: &extra_args,
+                opt_sender,
+                stdin_pipe: Arc::new(RwLock::new(None)),
+                running: Arc::new(RwLock::new(None)),
+            },
+            ["l", "test.7z", "-p1"],
+        )
+        .await
+        .unwrap();
+        assert!(status.success());
+        assert_eq!(output.await.unwrap()[0], "l -mmt=4 test.7z -p1");
+    }
+
+    #[tokio::test]
+    async fn test_relist_with_password() {
+        // 7z l prompts the password of a headers encrypted archive, list is running
+        let status = ExecuteStatus::Pedding;
+        assert!(!status.relist_with_password());
+        // wrong password, 7z exit with 2
+        let status = ExecuteStatus::List(ExitStatus::from_raw(2 << 8));
+        assert!(status.relist_with_password());
+        // the new password lists successfully
+        let status = ExecuteStatus::None;
+        assert!(!status.relist_with_password());
+        // a failed extract does not list again
+        let status = ExecuteStatus::Extract(ExitStatus::from_raw(2 << 8));
+        assert!(!status.relist_with_password());
+        assert!(!status.extracted());
+        assert!(ExecuteStatus::Extract(ExitStatus::from_raw(0)).extracted());
+
+        // the list failed with a wrong password, the typed one lists again
+        let matches = Options::command()
+            .try_get_matches_from(["vui-7z", "test.7z"])
+            .unwrap();
+        let opt = Options::from_arg_matches(&matches).unwrap();
+        let (doc_sender, _doc_recv) = mpsc::channel(8);
+        let z = Z7::new(doc_sender, &opt);
+        *z.execute_status.write().await = ExecuteStatus::List(ExitStatus::from_raw(2 << 8));
+        let (cmd_sender, mut cmd_recv) = mpsc::channel(8);
+        let (oper_sender, oper_recv) = mpsc::channel(8);
+        let mut operations = z.clone();
+        let task =
+            tokio::spawn(async move { operations.operation_make(cmd_sender, oper_recv).await });
+        oper_sender
+            .send(Operation::Password("123456".to_string()))
+            .await
+            .unwrap();
+        assert!(matches!(cmd_recv.recv().await, Some(Cmd::List)));
+        assert_eq!(z.password.read().await.as_deref(), Some("123456"));
+        drop(oper_sender);
+        task.await.unwrap().unwrap();
+    }
+
+    #[test]
+    fn test_exit_code() {
+        let ok = ExitStatus::from_raw(0);
+        let failed = ExitStatus::from_raw(2 << 8);
+        assert_eq!(ExecuteStatus::None.exit_code(None), ExitCode::Success);
+        assert_eq!(
+            ExecuteStatus::Extract(ok).exit_code(None),
+            ExitCode::Success
+        );
+        assert_eq!(ExecuteStatus::Pedding.exit_code(None), ExitCode::Quit);
+        assert_eq!(
+            ExecuteStatus::List(failed).exit_code(Some(Failure::WrongPassword)),
+            ExitCode::WrongPassword
+        );
+        assert_eq!(
+            ExecuteStatus::Test(failed).exit_code(Some(Failure::Corrupt)),
+            ExitCode::Corrupt
+        );
+        assert_eq!(ExecuteStatus::Add(failed).exit_code(None), ExitCode::Failed);
+        assert_eq!(
+            ExecuteStatus::Info(failed).exit_code(None),
+            ExitCode::Failed
+        );
+        assert_eq!(ExitCode::Quit as i32, 4);
+    }
+
+    #[test]
+    fn test_write_selected_password() {
+        // 7z waits on "Enter password", answer it directly
+        assert!(ExecuteStatus::Pedding.write_selected_password(true));
+        // running but the pipe is gone, the password was written or not asked yet
+        assert!(!ExecuteStatus::Pedding.write_selected_password(false));
+        // the list failed with a wrong password, list again with it queued
+        let status = ExecuteStatus::List(ExitStatus::from_raw(2 << 8));
+        assert!(!status.write_selected_password(false));
+        assert!(!ExecuteStatus::None.write_selected_password(false));
+        // a stale pipe of a finished command is never written
+        assert!(!ExecuteStatus::None.write_selected_password(true));
+    }
+
+    #[tokio::test]
+    async fn test_pending_export() {
+        let matches = Options::command()
+            .try_get_matches_from(["vui-7z", "test.7z"])
+            .unwrap();
+        let opt = Options::from_arg_matches(&matches).unwrap();
+        let (doc_sender, _doc_recv) = mpsc::channel(8);
+        let z = Z7::new(doc_sender, &opt);
+        *z.execute_status.write().await = ExecuteStatus::Pedding;
+        let (cmd_sender, _cmd_recv) = mpsc::channel(8);
+        let (oper_sender, oper_recv) = mpsc::channel(8);
+        let mut operations = z.clone();
+        let task =
+            tokio::spawn(async move { operations.operation_make(cmd_sender, oper_recv).await });
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("list.txt");
+        oper_sender
+            .send(Operation::ExportList(file.clone(), false))
+            .await
+            .unwrap();
+        // 7z waits on the password, it is still taken
+        oper_sender
+            .send(Operation::Password("123456".to_string()))
+            .await
+            .unwrap();
+        drop(oper_sender);
+        task.await.unwrap().unwrap();
+        assert_eq!(z.password.read().await.as_deref(), Some("123456"));
+        assert!(!file.exists());
+        // the command finished
+        *z.execute_status.write().await = ExecuteStatus::None;
+        z.run_pending_exports().await.unwrap();
+        assert!(file.exists());
+        assert!(z.pending_exports.read().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_mask_password() {
+        assert_eq!(mask("密码pwd"), "*****");
+        let matches = Options::command()
+            .try_get_matches_from(["vui-7z", "test.7z", "--mask-password"])
+            .unwrap();
+        let opt = Options::from_arg_matches(&matches).unwrap();
+        let (doc_sender, _doc_recv) = mpsc::channel(8);
+        let mut z = Z7::new(doc_sender, &opt);
+        let (cmd_sender, _cmd_recv) = mpsc::channel(8);
+        let (oper_sender, oper_recv) = mpsc::channel(8);
+        let mut operations = z.clone();
+        let task =
+            tokio::spawn(async move { operations.operation_make(cmd_sender, oper_recv).await });
+        z.remember_password("secret").await;
+        // the echoed stars are not a new password
+        oper_sender
+            .send(Operation::Password("******".to_string()))
+            .await
+            .unwrap();
+        // a password of stars can still be typed
+        oper_sender
+            .send(Operation::Password("****".to_string()))
+            .await
+            .unwrap();
+        drop(oper_sender);
+        task.await.unwrap().unwrap();
+        assert_eq!(z.password.read().await.as_deref(), Some("****"));
+        assert_eq!(z.masked_password.read().await.as_deref(), Some("****"));
+    }
+
+    #[test]
+    fn test_history_password() {
+        let file = std::env::temp_dir().join("vui-7z-test-history-password.txt");
+        std::fs::write(&file, "123456\n\nhello\n").unwrap();
+        let file_str = file.to_str().unwrap();
+        assert_eq!(
+            history_password(file_str, "1: 123456"),
+            Some("123456".to_string())
+        );
+        assert_eq!(
+            history_password(file_str, "2: *****"),
+            Some("hello".to_string())
+        );
+        assert_eq!(history_password(file_str, "0: x"), None);
+        assert_eq!(history_password(file_str, "3"), None);
+        assert_eq!(history_password(file_str, "hello"), None);
+        std::fs::remove_file(&file).unwrap();
+    }
+
+    #[test]
+    fn test_load_last_extract_path() {
+        let dir = std::env::temp_dir();
+        let file = dir.join("vui-7z-test-last-extract-path.txt");
+        std::fs::write(&file, format!("{}\n", dir.to_str().unwrap())).unwrap();
+        assert_eq!(load_last_extract_path(&file), Some(dir.clone()));
+        // stale entry
+        std::fs::write(&file, dir.join("vui-7z-not-exists").to_str().unwrap()).unwrap();
+        assert_eq!(load_last_extract_path(&file), None);
+        std::fs::remove_file(&file).unwrap();
+        assert_eq!(load_last_extract_path(&file), None);
+    }
+
+    #[test]
+    fn test_load_candidates() {
+        let file = std::env::temp_dir().join("vui-7z-test-candidates.txt");
+        std::fs::write(&file, "first\n\n  second  \n").unwrap();
+        let candidates = load_candidates(file.to_str().unwrap());
+        assert_eq!(candidates, ["first", "second"]);
+        std::fs::remove_file(&file).unwrap();
+        assert!(load_candidates(file.to_str().unwrap()).is_empty());
+    }
+
+    #[test]
+    fn test_spawn_error() {
+        assert!(find_binary("sh").is_some());
+        assert!(find_binary("/bin/sh").is_some());
+        assert!(find_binary("vui-7z-no-such-binary").is_none());
+
+        let mut doc = Document::new();
+        doc.layout_list();
+        let e = std::io::Error::from(std::io::ErrorKind::NotFound);
+        doc.input(&spawn_error_line("7zz", &e));
+        assert!(doc.output().contains(
+            &"ERROR: 7z binary not found: 7zz, install p7zip or set it with --bin".to_string()
+        ));
+    }
+
+    #[test]
+    fn test_archive_stem() {
+        assert_eq!(archive_stem("/home/someone/archive.tar.gz.001"), "archive");
+        assert_eq!(archive_stem("/home/someone/archive.7z.002"), "archive");
+        assert_eq!(archive_stem("/home/someone/archive.7z"), "archive");
+        assert_eq!(archive_stem("/home/someone/archive.tar.gz"), "archive");
+        assert_eq!(archive_stem("/home/someone/my.archive.zip"), "my.archive");
+        assert_eq!(archive_stem("archive"), "archive");
+    }
+
     #[test]
     fn test_check_same_prefix() {
         let files = ["test/03-e_03.png", "test/01-e_01.png"];
@@ -646,5 +3555,144 @@ mod test {
             prefix,
             Some("[陰謀の帝国 (印度カリー)] 蝶子系列 I_V/".to_string())
         );
+
+        // windows separators
+        let files = ["test\\a.png", "test\\b.png"];
+        let files = files.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        let prefix = check_same_directory(&files);
+        assert_eq!(prefix, Some("test/".to_string()));
+
+        let files = ["test", "test\\sub\\a.png", "test/b.png"];
+        let files = files.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        let prefix = check_same_directory(&files);
+        assert_eq!(prefix, Some("test/".to_string()));
+
+        let files = ["test\\a.png", "test2\\b.png"];
+        let files = files.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        let prefix = check_same_directory(&files);
+        assert_eq!(prefix, None);
+    }
+
+    #[test]
+    fn test_detail_lines() {
+        let output = r##"
+Listing archive: /tmp/test.7z
+
+--
+Path = /tmp/test.7z
+Type = 7z
+
+----------
+Path = test/01-e_01.png
+Size = 344963
+CRC = 4A1B2C3D
+Encrypted = -
+
+Path = test/01-e_01.png.bak
+Size = 12
+"##;
+        let lines = detail_lines(output, "test/01-e_01.png");
+        assert_eq!(
+            lines,
+            vec![
+                "Path = test/01-e_01.png",
+                "Size = 344963",
+                "CRC = 4A1B2C3D",
+                "Encrypted = -"
+            ]
+        );
+        assert_eq!(
+            detail_lines(&output.replace('\n', "\r\n"), "test/01-e_01.png"),
+            lines
+        );
+        // the archive block above the dash line is not a file
+        assert_eq!(
+            detail_lines(output, "/tmp/test.7z"),
+            vec!["no details of /tmp/test.7z"]
+        );
+    }
+
+    #[test]
+    fn test_execute_confirm() {
+        let mut confirm = ExecuteConfirm::default();
+        let now = Instant::now();
+        assert!(!confirm.confirm(now));
+        assert!(confirm.confirm(now + Duration::from_secs(1)));
+        // disarmed after running
+        assert!(!confirm.confirm(now + Duration::from_secs(2)));
+        // too late, arms again
+        let late = now + Duration::from_secs(2) + CONFIRM_TIMEOUT + Duration::from_secs(1);
+        assert!(!confirm.confirm(late));
+        assert!(confirm.confirm(late));
+    }
+
+    #[tokio::test]
+    async fn test_list_cache() {
+        let stdout = "Listing archive: test.7z\n1 file\n";
+        let (opt_sender, mut opt_recv) = mpsc::channel(1);
+        let (tee_sender, recorded) = record_output(opt_sender.clone());
+        let reader = tokio::spawn(read_output(stdout.as_bytes(), &b""[..], tee_sender));
+        let mut first = vec![];
+        while let Some(Some(line)) = opt_recv.recv().await {
+            first.push(line);
+        }
+        reader.await.unwrap().unwrap();
+        let lines = recorded.await.unwrap();
+        assert_eq!(lines, first);
+        assert!(lines.contains(&(b"Listing archive: test.7z".to_vec(), 1)));
+        // replayed the same, ended as a finished command
+        let replay = tokio::spawn(async move { replay_output(&lines, opt_sender).await });
+        let mut second = vec![];
+        while let Some(Some(line)) = opt_recv.recv().await {
+            second.push(line);
+        }
+        assert!(replay.await.unwrap().unwrap().success());
+        assert_eq!(second, first);
+        let now = SystemTime::now();
+        let cache = ListCache {
+            file: "test.7z".to_string(),
+            modified: now,
+            lines: second,
+        };
+        assert!(cache.hit("test.7z", now));
+        assert!(!cache.hit("test.7z", now + Duration::from_secs(1)));
+        assert!(!cache.hit("other.7z", now));
+    }
+
+    #[test]
+    fn test_find_volumes() {
+        let dir = std::env::temp_dir().join(format!("vui-7z-test-volumes-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for (name, size) in [
+            ("a.7z.002", 2),
+            ("a.7z.010", 10),
+            ("a.7z.001", 1),
+            ("b.7z.001", 1),
+            ("a.7z", 7),
+            ("c.part2.rar", 2),
+            ("c.part1.rar", 1),
+            ("single.zip", 3),
+        ] {
+            std::fs::write(dir.join(name), vec![0u8; size]).unwrap();
+        }
+        assert_eq!(
+            find_volumes(&dir.join("a.7z.002")),
+            vec![
+                ("a.7z.001".to_string(), 1),
+                ("a.7z.002".to_string(), 2),
+                ("a.7z.010".to_string(), 10)
+            ]
+        );
+        assert_eq!(
+            find_volumes(&dir.join("c.part1.rar")),
+            vec![
+                ("c.part1.rar".to_string(), 1),
+                ("c.part2.rar".to_string(), 2)
+            ]
+        );
+        // a lonely first volume is a single file too
+        assert!(find_volumes(&dir.join("b.7z.001")).is_empty());
+        assert!(find_volumes(&dir.join("single.zip")).is_empty());
+        std::fs::remove_dir_all(&dir).unwrap();
     }
 }